@@ -1,5 +1,5 @@
 use std::cmp::PartialEq;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 use std::fmt::Debug;
 use std::hash::Hash;
 
@@ -29,8 +29,7 @@ pub mod directed_graph {
         pub fn depth_first_vec(&self) -> Vec<T> {
             let mut stack: Vec<&Node<T>> = vec![self];
             let mut list: Vec<T> = vec![];
-            while !stack.is_empty() {
-                let current = stack.pop().unwrap();
+            while let Some(current) = stack.pop() {
                 list.push(current.value.clone());
                 for node in current.children.iter() {
                     stack.push(node.as_ref());
@@ -52,8 +51,7 @@ pub mod directed_graph {
         }
         pub fn contains(&self, value: T) -> bool {
             let mut stack: Vec<&Node<T>> = vec![self];
-            while !stack.is_empty() {
-                let current = stack.pop().unwrap();
+            while let Some(current) = stack.pop() {
                 if current.value == value {
                     return true;
                 }
@@ -137,16 +135,12 @@ pub mod graph {
         }
 
         pub fn edges_from_to(&self, from: &Nid, to: &Nid) -> Option<Vec<&E>> {
-            if let Some(vec) = self.adjacent.get(from) {
-                Some(
-                    vec.iter()
-                        .filter(|(node, _)| node == to)
-                        .map(|(_, edge)| edge)
-                        .collect::<Vec<&E>>(),
-                )
-            } else {
-                None
-            }
+            self.adjacent.get(from).map(|vec| {
+                vec.iter()
+                    .filter(|(node, _)| node == to)
+                    .map(|(_, edge)| edge)
+                    .collect::<Vec<&E>>()
+            })
         }
 
         pub fn edge_count(&self, from: &Nid, to: &Nid) -> usize {
@@ -168,6 +162,61 @@ pub mod graph {
         pub fn contains(&self, node_id: &Nid) -> bool {
             self.nodes.contains_key(node_id)
         }
+
+        pub fn node_ids(&self) -> Vec<&Nid> {
+            let mut seen: HashSet<&Nid> = HashSet::new();
+            let edge_ids = self.adjacent.iter().flat_map(|(from, edges)| {
+                std::iter::once(from).chain(edges.iter().map(|(to, _)| to))
+            });
+            self.nodes
+                .keys()
+                .chain(edge_ids)
+                .filter(|node_id| seen.insert(*node_id))
+                .collect()
+        }
+    }
+
+    impl<Nid, N, E> Graph<Nid, N, E>
+    where
+        Nid: Hash + Eq,
+    {
+        pub(crate) fn index_nodes(&self) -> (Vec<&Nid>, HashMap<&Nid, usize>) {
+            let node_ids = self.node_ids();
+            let index = node_ids
+                .iter()
+                .enumerate()
+                .map(|(i, node_id)| (*node_id, i))
+                .collect();
+            (node_ids, index)
+        }
+
+        pub(crate) fn successor_lists(&self, index: &HashMap<&Nid, usize>) -> Vec<Vec<usize>> {
+            let mut successors = vec![vec![]; index.len()];
+            for (from, edges) in self.adjacent.iter() {
+                successors[index[from]].extend(edges.iter().map(|(to, _)| index[to]));
+            }
+            successors
+        }
+
+        pub(crate) fn neighbour_lists(&self, index: &HashMap<&Nid, usize>) -> Vec<Vec<usize>> {
+            let mut neighbours = vec![vec![]; index.len()];
+            for (from, edges) in self.adjacent.iter() {
+                for (to, _) in edges.iter() {
+                    neighbours[index[from]].push(index[to]);
+                    neighbours[index[to]].push(index[from]);
+                }
+            }
+            neighbours
+        }
+    }
+
+    impl<Nid, N, E> Default for Graph<Nid, N, E>
+    where
+        Nid: Hash + Eq,
+    {
+        fn default() -> Self {
+            Self::new()
+        }
     }
 
     impl<Nid, N, E> Graph<Nid, N, E>
@@ -205,4 +254,7684 @@ pub mod graph {
             }
         }
     }
+
+    impl<Nid, N, E> Graph<Nid, N, E>
+    where
+        Nid: Hash + Eq,
+        E: PartialEq,
+    {
+        // Each edge is reported once, paired with its reverse twin the way
+        // `push_undirected_edge` stores it, one-way edges are reported as they are.
+        pub fn undirected_edges(&self) -> Vec<(&Nid, &Nid, &E)> {
+            let mut unpaired: HashMap<(&Nid, &Nid), Vec<&E>> = HashMap::new();
+            let mut edges = vec![];
+            for (from, adjacent) in self.adjacent.iter() {
+                for (to, edge) in adjacent.iter() {
+                    let twins = unpaired.entry((to, from)).or_default();
+                    if let Some(position) = twins.iter().position(|twin| *twin == edge) {
+                        twins.swap_remove(position);
+                        edges.push((from, to, edge));
+                    } else {
+                        unpaired.entry((from, to)).or_default().push(edge);
+                    }
+                }
+            }
+            for ((from, to), rest) in unpaired {
+                edges.extend(rest.into_iter().map(|edge| (from, to, edge)));
+            }
+            edges
+        }
+    }
+}
+
+pub mod shortest_path {
+    use super::graph::Graph;
+    use super::*;
+    use std::cmp::Ordering;
+    use std::ops::{Add, Sub};
+
+    pub trait Measure: Debug + PartialOrd + Add<Output = Self> + Default + Copy {}
+
+    impl<M> Measure for M where M: Debug + PartialOrd + Add<Output = M> + Default + Copy {}
+
+    #[derive(Clone, Debug)]
+    pub struct ShortestPaths<Nid, K> {
+        pub distances: HashMap<Nid, K>,
+        pub predecessors: HashMap<Nid, Nid>,
+    }
+
+    impl<Nid, K> ShortestPaths<Nid, K>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Copy,
+    {
+        pub fn distance(&self, target: &Nid) -> Option<K> {
+            self.distances.get(target).copied()
+        }
+
+        pub fn path_to(&self, target: &Nid) -> Option<Vec<Nid>> {
+            if !self.distances.contains_key(target) {
+                return None;
+            }
+            let mut path = vec![target.clone()];
+            let mut current = target;
+            while let Some(previous) = self.predecessors.get(current) {
+                path.push(previous.clone());
+                current = previous;
+            }
+            path.reverse();
+            Some(path)
+        }
+    }
+
+    // Heap entry ordered by smallest score first, incomparable scores (NaN) sink to the bottom.
+    #[derive(Clone, Debug)]
+    pub(crate) struct MinScored<K, T>(pub(crate) K, pub(crate) T);
+
+    impl<K: PartialOrd, T> PartialEq for MinScored<K, T> {
+        fn eq(&self, other: &Self) -> bool {
+            self.cmp(other) == Ordering::Equal
+        }
+    }
+
+    impl<K: PartialOrd, T> Eq for MinScored<K, T> {}
+
+    impl<K: PartialOrd, T> PartialOrd for MinScored<K, T> {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl<K: PartialOrd, T> Ord for MinScored<K, T> {
+        fn cmp(&self, other: &Self) -> Ordering {
+            let (a, b) = (&self.0, &other.0);
+            match a.partial_cmp(b) {
+                Some(ordering) => ordering.reverse(),
+                None =>
+                {
+                    #[allow(clippy::eq_op)]
+                    match (a != a, b != b) {
+                        (true, false) => Ordering::Less,
+                        (false, true) => Ordering::Greater,
+                        _ => Ordering::Equal,
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn dijkstra<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        source: Nid,
+        mut edge_cost: F,
+    ) -> ShortestPaths<Nid, K>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure,
+        F: FnMut(&E) -> K,
+    {
+        dijkstra_with_endpoints(graph, source, |_, _, edge| edge_cost(edge))
+    }
+
+    pub(crate) fn dijkstra_with_endpoints<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        source: Nid,
+        mut edge_cost: F,
+    ) -> ShortestPaths<Nid, K>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure,
+        F: FnMut(&Nid, &Nid, &E) -> K,
+    {
+        let mut distances: HashMap<Nid, K> = HashMap::new();
+        let mut predecessors: HashMap<Nid, Nid> = HashMap::new();
+        let mut visited: HashSet<Nid> = HashSet::new();
+        let mut heap = BinaryHeap::new();
+
+        distances.insert(source.clone(), K::default());
+        heap.push(MinScored(K::default(), source));
+
+        while let Some(MinScored(cost, node)) = heap.pop() {
+            if visited.contains(&node) {
+                continue;
+            }
+            if let Some(edges) = graph.edges_from(&node) {
+                for (next, edge) in edges.iter() {
+                    if visited.contains(next) {
+                        continue;
+                    }
+                    let next_cost = cost + edge_cost(&node, next, edge);
+                    let improved = match distances.get(next) {
+                        Some(current) => next_cost < *current,
+                        None => true,
+                    };
+                    if improved {
+                        distances.insert(next.clone(), next_cost);
+                        predecessors.insert(next.clone(), node.clone());
+                        heap.push(MinScored(next_cost, next.clone()));
+                    }
+                }
+            }
+            visited.insert(node);
+        }
+
+        ShortestPaths {
+            distances,
+            predecessors,
+        }
+    }
+
+    // Optimal for any admissible heuristic: a closed node is reopened when a
+    // cheaper route to it turns up, which only happens for inconsistent ones.
+    pub fn astar<Nid, N, E, K, G, F, H>(
+        graph: &Graph<Nid, N, E>,
+        start: Nid,
+        mut is_goal: G,
+        mut edge_cost: F,
+        mut heuristic: H,
+    ) -> Option<(K, Vec<Nid>)>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure,
+        G: FnMut(&Nid) -> bool,
+        F: FnMut(&E) -> K,
+        H: FnMut(&Nid) -> K,
+    {
+        let mut scores: HashMap<Nid, K> = HashMap::new();
+        let mut predecessors: HashMap<Nid, Nid> = HashMap::new();
+        let mut closed: HashSet<Nid> = HashSet::new();
+        let mut heap = BinaryHeap::new();
+
+        scores.insert(start.clone(), K::default());
+        heap.push(MinScored(heuristic(&start), start));
+
+        while let Some(MinScored(_, node)) = heap.pop() {
+            if closed.contains(&node) {
+                continue;
+            }
+            let cost = scores[&node];
+            if is_goal(&node) {
+                let paths = ShortestPaths {
+                    distances: scores,
+                    predecessors,
+                };
+                return paths.path_to(&node).map(|path| (cost, path));
+            }
+            if let Some(edges) = graph.edges_from(&node) {
+                for (next, edge) in edges.iter() {
+                    let next_cost = cost + edge_cost(edge);
+                    let improved = match scores.get(next) {
+                        Some(current) => next_cost < *current,
+                        None => true,
+                    };
+                    if improved {
+                        closed.remove(next);
+                        scores.insert(next.clone(), next_cost);
+                        predecessors.insert(next.clone(), node.clone());
+                        heap.push(MinScored(next_cost + heuristic(next), next.clone()));
+                    }
+                }
+            }
+            closed.insert(node);
+        }
+        None
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct NegativeCycle<Nid>(pub Vec<Nid>);
+
+    fn relax_edges<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        distances: &mut HashMap<Nid, K>,
+        predecessors: &mut HashMap<Nid, Nid>,
+        edge_cost: &mut F,
+    ) -> Option<Nid>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure,
+        F: FnMut(&E) -> K,
+    {
+        let mut relaxed = None;
+        for (from, edges) in graph.iter_edges() {
+            for (to, edge) in edges.iter() {
+                let cost = match distances.get(from) {
+                    Some(cost) => *cost,
+                    None => break,
+                };
+                let next_cost = cost + edge_cost(edge);
+                let improved = match distances.get(to) {
+                    Some(current) => next_cost < *current,
+                    None => true,
+                };
+                if improved {
+                    distances.insert(to.clone(), next_cost);
+                    predecessors.insert(to.clone(), from.clone());
+                    relaxed = Some(to.clone());
+                }
+            }
+        }
+        relaxed
+    }
+
+    fn find_cycle<Nid>(predecessors: &HashMap<Nid, Nid>, start: Nid) -> Option<Vec<Nid>>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        let mut walked: Vec<Nid> = vec![];
+        let mut position: HashMap<Nid, usize> = HashMap::new();
+        let mut current = start;
+        loop {
+            if let Some(&index) = position.get(&current) {
+                let mut cycle = walked.split_off(index);
+                cycle.reverse();
+                return Some(cycle);
+            }
+            position.insert(current.clone(), walked.len());
+            walked.push(current.clone());
+            current = predecessors.get(&current)?.clone();
+        }
+    }
+
+    pub fn bellman_ford<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        source: Nid,
+        mut edge_cost: F,
+    ) -> Result<ShortestPaths<Nid, K>, NegativeCycle<Nid>>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure,
+        F: FnMut(&E) -> K,
+    {
+        let node_count = graph.node_ids().len().max(1);
+        let mut distances: HashMap<Nid, K> = HashMap::new();
+        let mut predecessors: HashMap<Nid, Nid> = HashMap::new();
+        distances.insert(source, K::default());
+
+        let mut converged = false;
+        for _ in 1..node_count {
+            if relax_edges(graph, &mut distances, &mut predecessors, &mut edge_cost).is_none() {
+                converged = true;
+                break;
+            }
+        }
+        if !converged {
+            if let Some(node) =
+                relax_edges(graph, &mut distances, &mut predecessors, &mut edge_cost)
+            {
+                let cycle = find_cycle(&predecessors, node)
+                    .expect("relaxation after |V| - 1 rounds implies a predecessor cycle");
+                return Err(NegativeCycle(cycle));
+            }
+        }
+
+        Ok(ShortestPaths {
+            distances,
+            predecessors,
+        })
+    }
+
+    pub fn spfa<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        source: Nid,
+        mut edge_cost: F,
+    ) -> Result<ShortestPaths<Nid, K>, NegativeCycle<Nid>>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure,
+        F: FnMut(&E) -> K,
+    {
+        let node_count = graph.node_ids().len().max(1);
+        let mut distances: HashMap<Nid, K> = HashMap::new();
+        let mut predecessors: HashMap<Nid, Nid> = HashMap::new();
+        let mut lengths: HashMap<Nid, usize> = HashMap::new();
+        let mut queued: HashSet<Nid> = HashSet::new();
+        let mut queue: VecDeque<Nid> = VecDeque::new();
+
+        distances.insert(source.clone(), K::default());
+        lengths.insert(source.clone(), 0);
+        queued.insert(source.clone());
+        queue.push_back(source.clone());
+
+        while let Some(node) = queue.pop_front() {
+            queued.remove(&node);
+            let cost = distances[&node];
+            let length = lengths[&node];
+            if let Some(edges) = graph.edges_from(&node) {
+                for (next, edge) in edges.iter() {
+                    let next_cost = cost + edge_cost(edge);
+                    let improved = match distances.get(next) {
+                        Some(current) => next_cost < *current,
+                        None => true,
+                    };
+                    if !improved {
+                        continue;
+                    }
+                    distances.insert(next.clone(), next_cost);
+                    predecessors.insert(next.clone(), node.clone());
+                    lengths.insert(next.clone(), length + 1);
+                    if length + 1 >= node_count {
+                        // The predecessor walk usually closes the cycle already, if it
+                        // does not yet, Bellman-Ford will find it.
+                        return match find_cycle(&predecessors, next.clone()) {
+                            Some(cycle) => Err(NegativeCycle(cycle)),
+                            None => bellman_ford(graph, source, edge_cost),
+                        };
+                    }
+                    if queued.insert(next.clone()) {
+                        queue.push_back(next.clone());
+                    }
+                }
+            }
+        }
+
+        Ok(ShortestPaths {
+            distances,
+            predecessors,
+        })
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct DistanceMatrix<Nid, K> {
+        nodes: Vec<Nid>,
+        index: HashMap<Nid, usize>,
+        distances: Vec<Option<K>>,
+        next: Vec<Option<usize>>,
+    }
+
+    impl<Nid, K> DistanceMatrix<Nid, K>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure,
+    {
+        fn with_nodes(nodes: Vec<Nid>) -> Self {
+            let size = nodes.len();
+            let index = nodes
+                .iter()
+                .enumerate()
+                .map(|(i, node_id)| (node_id.clone(), i))
+                .collect();
+            let mut matrix = DistanceMatrix {
+                nodes,
+                index,
+                distances: vec![None; size * size],
+                next: vec![None; size * size],
+            };
+            for i in 0..size {
+                matrix.distances[i * size + i] = Some(K::default());
+                matrix.next[i * size + i] = Some(i);
+            }
+            matrix
+        }
+
+        pub fn nodes(&self) -> &[Nid] {
+            &self.nodes
+        }
+
+        pub fn distance(&self, from: &Nid, to: &Nid) -> Option<K> {
+            let (i, j) = (*self.index.get(from)?, *self.index.get(to)?);
+            self.distances[i * self.nodes.len() + j]
+        }
+
+        pub fn next_hop(&self, from: &Nid, to: &Nid) -> Option<&Nid> {
+            let (i, j) = (*self.index.get(from)?, *self.index.get(to)?);
+            self.next[i * self.nodes.len() + j].map(|hop| &self.nodes[hop])
+        }
+
+        pub fn path(&self, from: &Nid, to: &Nid) -> Option<Vec<Nid>> {
+            let (mut i, j) = (*self.index.get(from)?, *self.index.get(to)?);
+            let size = self.nodes.len();
+            self.next[i * size + j]?;
+            let mut path = vec![self.nodes[i].clone()];
+            while i != j {
+                i = self.next[i * size + j]?;
+                path.push(self.nodes[i].clone());
+            }
+            Some(path)
+        }
+    }
+
+    pub fn floyd_warshall<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        mut edge_cost: F,
+    ) -> Result<DistanceMatrix<Nid, K>, NegativeCycle<Nid>>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure,
+        F: FnMut(&E) -> K,
+    {
+        let nodes: Vec<Nid> = graph.node_ids().into_iter().cloned().collect();
+        let size = nodes.len();
+        let mut matrix = DistanceMatrix::with_nodes(nodes);
+
+        for (from, edges) in graph.iter_edges() {
+            let i = matrix.index[from];
+            for (to, edge) in edges.iter() {
+                let j = matrix.index[to];
+                let cost = edge_cost(edge);
+                let improved = match matrix.distances[i * size + j] {
+                    Some(current) => cost < current,
+                    None => true,
+                };
+                if improved {
+                    matrix.distances[i * size + j] = Some(cost);
+                    matrix.next[i * size + j] = Some(j);
+                }
+            }
+        }
+        for i in 0..size {
+            if matrix.distances[i * size + i].is_some_and(|cost| cost < K::default()) {
+                return Err(NegativeCycle(vec![matrix.nodes[i].clone()]));
+            }
+        }
+        let direct = matrix.distances.clone();
+
+        for k in 0..size {
+            for i in 0..size {
+                let through = match matrix.distances[i * size + k] {
+                    Some(through) => through,
+                    None => continue,
+                };
+                for j in 0..size {
+                    let rest = match matrix.distances[k * size + j] {
+                        Some(rest) => rest,
+                        None => continue,
+                    };
+                    let cost = through + rest;
+                    let improved = match matrix.distances[i * size + j] {
+                        Some(current) => cost < current,
+                        None => true,
+                    };
+                    if improved {
+                        matrix.distances[i * size + j] = Some(cost);
+                        matrix.next[i * size + j] = matrix.next[i * size + k];
+                    }
+                    // Relaxing any further around the cycle only drives the costs
+                    // towards overflow.
+                    if i == j && cost < K::default() {
+                        return Err(NegativeCycle(negative_cycle(&matrix, &direct, i, k)));
+                    }
+                }
+            }
+        }
+
+        Ok(matrix)
+    }
+
+    // Until the first diagonal entry goes negative the hops through `k` still
+    // follow simple paths, so `i` to `k` and back is a closed walk of negative
+    // cost. One of the simple cycles it splits into has to be negative as well.
+    fn negative_cycle<Nid, K>(
+        matrix: &DistanceMatrix<Nid, K>,
+        direct: &[Option<K>],
+        i: usize,
+        k: usize,
+    ) -> Vec<Nid>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure,
+    {
+        let size = matrix.nodes.len();
+        let mut walk = vec![i];
+        for target in [k, i] {
+            let mut node = *walk.last().unwrap();
+            while node != target {
+                node = matrix.next[node * size + target].unwrap();
+                walk.push(node);
+            }
+        }
+
+        let mut position = vec![usize::MAX; size];
+        let mut stack: Vec<usize> = vec![];
+        for node in walk {
+            if position[node] == usize::MAX {
+                position[node] = stack.len();
+                stack.push(node);
+                continue;
+            }
+            let start = position[node];
+            let mut cost = K::default();
+            for (offset, &from) in stack[start..].iter().enumerate() {
+                let to = stack.get(start + offset + 1).copied().unwrap_or(node);
+                cost = cost + direct[from * size + to].unwrap();
+            }
+            if cost < K::default() {
+                return stack[start..]
+                    .iter()
+                    .map(|&node| matrix.nodes[node].clone())
+                    .collect();
+            }
+            for removed in stack.drain(start + 1..) {
+                position[removed] = usize::MAX;
+            }
+        }
+        stack
+            .into_iter()
+            .map(|node| matrix.nodes[node].clone())
+            .collect()
+    }
+
+    pub fn johnson<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        mut edge_cost: F,
+    ) -> Result<DistanceMatrix<Nid, K>, NegativeCycle<Nid>>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure + Sub<Output = K>,
+        F: FnMut(&E) -> K,
+    {
+        let nodes: Vec<Nid> = graph.node_ids().into_iter().cloned().collect();
+        let size = nodes.len();
+
+        // Starting every node at zero is the same as relaxing from a virtual source
+        // with zero-cost edges to all of them.
+        let mut potentials: HashMap<Nid, K> = nodes
+            .iter()
+            .map(|node_id| (node_id.clone(), K::default()))
+            .collect();
+        let mut predecessors: HashMap<Nid, Nid> = HashMap::new();
+        let mut converged = false;
+        for _ in 0..size {
+            if relax_edges(graph, &mut potentials, &mut predecessors, &mut edge_cost).is_none() {
+                converged = true;
+                break;
+            }
+        }
+        if !converged {
+            if let Some(node) =
+                relax_edges(graph, &mut potentials, &mut predecessors, &mut edge_cost)
+            {
+                let cycle = find_cycle(&predecessors, node)
+                    .expect("relaxation after |V| rounds implies a predecessor cycle");
+                return Err(NegativeCycle(cycle));
+            }
+        }
+
+        let mut matrix = DistanceMatrix::with_nodes(nodes);
+        for i in 0..size {
+            let source = matrix.nodes[i].clone();
+            let paths = dijkstra_with_endpoints(graph, source.clone(), |from, to, edge| {
+                edge_cost(edge) + potentials[from] - potentials[to]
+            });
+
+            let mut first_hops: HashMap<&Nid, usize> = HashMap::new();
+            for (target, reweighted) in paths.distances.iter() {
+                let j = matrix.index[target];
+                matrix.distances[i * size + j] =
+                    Some(*reweighted - potentials[&source] + potentials[target]);
+
+                let mut chain = vec![];
+                let mut current = target;
+                let hop = loop {
+                    if let Some(&hop) = first_hops.get(current) {
+                        break hop;
+                    }
+                    match paths.predecessors.get(current) {
+                        Some(previous) if *previous == source => break matrix.index[current],
+                        Some(previous) => {
+                            chain.push(current);
+                            current = previous;
+                        }
+                        None => break i,
+                    }
+                };
+                first_hops.insert(current, hop);
+                for node_id in chain {
+                    first_hops.insert(node_id, hop);
+                }
+                matrix.next[i * size + j] = Some(hop);
+            }
+        }
+
+        Ok(matrix)
+    }
+
+    pub fn manhattan_distance(a: &(i32, i32), b: &(i32, i32)) -> i32 {
+        (a.0 - b.0).abs() + (a.1 - b.1).abs()
+    }
+
+    pub fn euclidean_distance(a: &(i32, i32), b: &(i32, i32)) -> f64 {
+        let dx = (a.0 - b.0) as f64;
+        let dy = (a.1 - b.1) as f64;
+        (dx * dx + dy * dy).sqrt()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn roads() -> Graph<&'static str, (), u32> {
+            let mut graph = Graph::new();
+            graph.add_edge("a", "b", 7);
+            graph.add_edge("a", "c", 9);
+            graph.add_edge("a", "f", 14);
+            graph.add_edge("b", "c", 10);
+            graph.add_edge("b", "d", 15);
+            graph.add_edge("c", "d", 11);
+            graph.add_edge("c", "f", 2);
+            graph.add_edge("d", "e", 6);
+            graph.add_edge("f", "e", 9);
+            graph.insert_node("island", ());
+            graph
+        }
+
+        #[test]
+        fn dijkstra_finds_shortest_distances_and_paths() {
+            let paths = dijkstra(&roads(), "a", |cost| *cost);
+            assert_eq!(paths.distance(&"a"), Some(0));
+            assert_eq!(paths.distance(&"d"), Some(20));
+            assert_eq!(paths.distance(&"e"), Some(20));
+            assert_eq!(paths.path_to(&"e"), Some(vec!["a", "c", "f", "e"]));
+            assert_eq!(paths.path_to(&"a"), Some(vec!["a"]));
+        }
+
+        #[test]
+        fn dijkstra_leaves_unreachable_nodes_out() {
+            let paths = dijkstra(&roads(), "d", |cost| *cost);
+            assert_eq!(paths.distance(&"e"), Some(6));
+            assert_eq!(paths.distance(&"a"), None);
+            assert_eq!(paths.path_to(&"island"), None);
+            assert_eq!(paths.distances.len(), 2);
+        }
+
+        #[test]
+        fn dijkstra_accepts_float_weights() {
+            let mut graph: Graph<u8, (), f64> = Graph::new();
+            graph.add_edge(0, 1, 0.5);
+            graph.add_edge(1, 2, 0.25);
+            graph.add_edge(0, 2, 1.0);
+            let paths = dijkstra(&graph, 0, |cost| *cost);
+            assert_eq!(paths.distance(&2), Some(0.75));
+        }
+
+        #[test]
+        fn astar_reopens_nodes_for_admissible_heuristics() {
+            let mut graph: Graph<&str, (), u32> = Graph::new();
+            graph.add_edge("S", "A", 1);
+            graph.add_edge("A", "C", 1);
+            graph.add_edge("S", "C", 3);
+            graph.add_edge("C", "G", 10);
+            let heuristic = |node: &&str| if *node == "A" { 11 } else { 0 };
+            let found = astar(&graph, "S", |node| *node == "G", |cost| *cost, heuristic);
+            assert_eq!(found, Some((12, vec!["S", "A", "C", "G"])));
+        }
+
+        fn grid(walls: &[(i32, i32)]) -> Graph<(i32, i32), (), u32> {
+            let mut graph = Graph::new();
+            for x in 0..5 {
+                for y in 0..5 {
+                    for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                        let next = (x + dx, y + dy);
+                        let inside = (0..5).contains(&next.0) && (0..5).contains(&next.1);
+                        if inside && !walls.contains(&(x, y)) && !walls.contains(&next) {
+                            graph.add_edge((x, y), next, 1);
+                        }
+                    }
+                }
+            }
+            graph
+        }
+
+        #[test]
+        fn astar_routes_around_walls_with_manhattan_heuristic() {
+            let graph = grid(&[(1, 0), (1, 1), (1, 2), (1, 3)]);
+            let goal = (2, 0);
+            let (cost, path) = astar(
+                &graph,
+                (0, 0),
+                |node| *node == goal,
+                |cost| *cost,
+                |node| manhattan_distance(node, &goal) as u32,
+            )
+            .unwrap();
+            assert_eq!(cost, 10);
+            assert_eq!(path.len(), 11);
+            assert_eq!(path.first(), Some(&(0, 0)));
+            assert_eq!(path.last(), Some(&goal));
+        }
+
+        #[test]
+        fn astar_stops_at_the_first_goal_and_reports_unreachable_goals() {
+            let graph = grid(&[]);
+            let found = astar(&graph, (2, 2), |node| node.0 == 4, |cost| *cost, |_| 0);
+            assert_eq!(found.map(|(cost, _)| cost), Some(2));
+            let walled = grid(&[(3, 0), (3, 1), (3, 2), (3, 3), (3, 4)]);
+            let missing = astar(&walled, (0, 0), |node| node.0 == 4, |cost| *cost, |_| 0);
+            assert_eq!(missing, None);
+        }
+
+        #[test]
+        fn grid_heuristics_measure_distances() {
+            assert_eq!(manhattan_distance(&(0, 0), &(3, -4)), 7);
+            assert_eq!(euclidean_distance(&(0, 0), &(3, -4)), 5.0);
+        }
+
+        fn with_negative_edges() -> Graph<u32, (), i32> {
+            let mut graph = Graph::new();
+            graph.add_edge(0, 1, 4);
+            graph.add_edge(0, 2, 5);
+            graph.add_edge(1, 3, -3);
+            graph.add_edge(2, 1, -2);
+            graph.add_edge(3, 4, 2);
+            graph.add_edge(5, 0, 1);
+            graph
+        }
+
+        fn assert_negative_cycle(graph: &Graph<u32, (), i32>, cycle: &[u32]) {
+            assert!(!cycle.is_empty());
+            let mut total = 0;
+            for (position, from) in cycle.iter().enumerate() {
+                let to = cycle[(position + 1) % cycle.len()];
+                total += *graph.get_edge(from, &to).unwrap();
+            }
+            assert!(total < 0);
+        }
+
+        #[test]
+        fn bellman_ford_and_spfa_handle_negative_edges() {
+            let graph = with_negative_edges();
+            for paths in [
+                bellman_ford(&graph, 0, |cost| *cost).unwrap(),
+                spfa(&graph, 0, |cost| *cost).unwrap(),
+            ] {
+                assert_eq!(paths.distance(&1), Some(3));
+                assert_eq!(paths.distance(&3), Some(0));
+                assert_eq!(paths.distance(&4), Some(2));
+                assert_eq!(paths.distance(&5), None);
+                assert_eq!(paths.path_to(&4), Some(vec![0, 2, 1, 3, 4]));
+            }
+        }
+
+        #[test]
+        fn bellman_ford_and_spfa_report_reachable_negative_cycles() {
+            let mut graph = with_negative_edges();
+            graph.add_edge(3, 2, 1);
+            let NegativeCycle(cycle) = bellman_ford(&graph, 0, |cost| *cost).unwrap_err();
+            assert_negative_cycle(&graph, &cycle);
+            let NegativeCycle(cycle) = spfa(&graph, 0, |cost| *cost).unwrap_err();
+            assert_negative_cycle(&graph, &cycle);
+
+            // A negative cycle the source cannot reach does not matter.
+            assert!(bellman_ford(&graph, 4, |cost| *cost).is_ok());
+            assert!(spfa(&graph, 4, |cost| *cost).is_ok());
+        }
+
+        #[test]
+        fn floyd_warshall_and_johnson_agree_on_all_pairs() {
+            let graph = with_negative_edges();
+            let floyd = floyd_warshall(&graph, |cost| *cost).unwrap();
+            let johnson = johnson(&graph, |cost| *cost).unwrap();
+            for from in graph.node_ids() {
+                for to in graph.node_ids() {
+                    assert_eq!(floyd.distance(from, to), johnson.distance(from, to));
+                }
+            }
+
+            assert_eq!(floyd.distance(&5, &4), Some(3));
+            assert_eq!(floyd.distance(&2, &2), Some(0));
+            assert_eq!(floyd.next_hop(&5, &4), Some(&0));
+            assert_eq!(floyd.path(&5, &4), Some(vec![5, 0, 2, 1, 3, 4]));
+            assert_eq!(johnson.path(&0, &3), Some(vec![0, 2, 1, 3]));
+        }
+
+        #[test]
+        fn all_pairs_leave_unreachable_pairs_out() {
+            let graph = with_negative_edges();
+            for matrix in [
+                floyd_warshall(&graph, |cost| *cost).unwrap(),
+                johnson(&graph, |cost| *cost).unwrap(),
+            ] {
+                assert_eq!(matrix.distance(&4, &0), None);
+                assert_eq!(matrix.next_hop(&4, &0), None);
+                assert_eq!(matrix.path(&4, &0), None);
+                assert_eq!(matrix.distance(&4, &9), None);
+            }
+        }
+
+        #[test]
+        fn all_pairs_report_negative_cycles() {
+            let mut graph = with_negative_edges();
+            graph.add_edge(3, 2, 1);
+            let NegativeCycle(cycle) = floyd_warshall(&graph, |cost| *cost).unwrap_err();
+            assert_negative_cycle(&graph, &cycle);
+            let NegativeCycle(cycle) = johnson(&graph, |cost| *cost).unwrap_err();
+            assert_negative_cycle(&graph, &cycle);
+
+            graph.add_edge(4, 4, -1);
+            let NegativeCycle(cycle) = floyd_warshall(&graph, |cost| *cost).unwrap_err();
+            assert_negative_cycle(&graph, &cycle);
+        }
+
+        #[test]
+        fn floyd_warshall_stops_before_a_negative_cycle_overflows() {
+            let mut graph: Graph<u32, (), i8> = Graph::new();
+            graph.add_edge(0, 1, -100);
+            graph.add_edge(1, 0, 50);
+            graph.add_edge(1, 2, -100);
+            let NegativeCycle(mut cycle) = floyd_warshall(&graph, |cost| *cost).unwrap_err();
+            cycle.sort();
+            assert_eq!(cycle, vec![0, 1]);
+        }
+    }
+}
+
+pub mod traversal {
+    use super::graph::Graph;
+    use super::*;
+
+    // All walkers share the same restart rules: `move_to` drops pending nodes and
+    // continues from the given one while keeping the visited set, `reset` also
+    // forgets what was visited.
+    pub trait Walker<Nid>: Sized {
+        fn walk_next<N, E>(&mut self, graph: &Graph<Nid, N, E>) -> Option<Nid>;
+
+        fn iter<N, E>(self, graph: &Graph<Nid, N, E>) -> WalkerIter<'_, Self, Nid, N, E> {
+            WalkerIter {
+                walker: self,
+                graph,
+            }
+        }
+    }
+
+    impl<W, Nid> Walker<Nid> for &mut W
+    where
+        W: Walker<Nid>,
+    {
+        fn walk_next<N, E>(&mut self, graph: &Graph<Nid, N, E>) -> Option<Nid> {
+            (**self).walk_next(graph)
+        }
+    }
+
+    pub struct WalkerIter<'a, W, Nid, N, E> {
+        walker: W,
+        graph: &'a Graph<Nid, N, E>,
+    }
+
+    impl<W, Nid, N, E> WalkerIter<'_, W, Nid, N, E> {
+        pub fn into_walker(self) -> W {
+            self.walker
+        }
+    }
+
+    impl<W, Nid, N, E> Iterator for WalkerIter<'_, W, Nid, N, E>
+    where
+        W: Walker<Nid>,
+    {
+        type Item = Nid;
+
+        fn next(&mut self) -> Option<Nid> {
+            self.walker.walk_next(self.graph)
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct Bfs<Nid> {
+        queue: VecDeque<Nid>,
+        visited: HashSet<Nid>,
+    }
+
+    impl<Nid> Bfs<Nid>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        pub fn new(start: Nid) -> Self {
+            let mut bfs = Bfs {
+                queue: VecDeque::new(),
+                visited: HashSet::new(),
+            };
+            bfs.move_to(start);
+            bfs
+        }
+
+        pub fn move_to(&mut self, start: Nid) {
+            self.queue.clear();
+            if self.visited.insert(start.clone()) {
+                self.queue.push_back(start);
+            }
+        }
+
+        pub fn reset(&mut self) {
+            self.queue.clear();
+            self.visited.clear();
+        }
+
+        pub fn is_visited(&self, node_id: &Nid) -> bool {
+            self.visited.contains(node_id)
+        }
+
+        pub fn visited(&self) -> &HashSet<Nid> {
+            &self.visited
+        }
+    }
+
+    impl<Nid> Walker<Nid> for Bfs<Nid>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        fn walk_next<N, E>(&mut self, graph: &Graph<Nid, N, E>) -> Option<Nid> {
+            let current = self.queue.pop_front()?;
+            if let Some(edges) = graph.edges_from(&current) {
+                for (next, _) in edges.iter() {
+                    if self.visited.insert(next.clone()) {
+                        self.queue.push_back(next.clone());
+                    }
+                }
+            }
+            Some(current)
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct Dfs<Nid> {
+        stack: Vec<Nid>,
+        visited: HashSet<Nid>,
+    }
+
+    impl<Nid> Dfs<Nid>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        pub fn new(start: Nid) -> Self {
+            Dfs {
+                stack: vec![start],
+                visited: HashSet::new(),
+            }
+        }
+
+        pub fn move_to(&mut self, start: Nid) {
+            self.stack.clear();
+            self.stack.push(start);
+        }
+
+        pub fn reset(&mut self) {
+            self.stack.clear();
+            self.visited.clear();
+        }
+
+        pub fn is_visited(&self, node_id: &Nid) -> bool {
+            self.visited.contains(node_id)
+        }
+
+        pub fn visited(&self) -> &HashSet<Nid> {
+            &self.visited
+        }
+    }
+
+    impl<Nid> Walker<Nid> for Dfs<Nid>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        fn walk_next<N, E>(&mut self, graph: &Graph<Nid, N, E>) -> Option<Nid> {
+            while let Some(current) = self.stack.pop() {
+                if !self.visited.insert(current.clone()) {
+                    continue;
+                }
+                if let Some(edges) = graph.edges_from(&current) {
+                    for (next, _) in edges.iter().rev() {
+                        if !self.visited.contains(next) {
+                            self.stack.push(next.clone());
+                        }
+                    }
+                }
+                return Some(current);
+            }
+            None
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct DfsPostOrder<Nid> {
+        stack: Vec<Nid>,
+        discovered: HashSet<Nid>,
+        finished: HashSet<Nid>,
+    }
+
+    impl<Nid> DfsPostOrder<Nid>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        pub fn new(start: Nid) -> Self {
+            DfsPostOrder {
+                stack: vec![start],
+                discovered: HashSet::new(),
+                finished: HashSet::new(),
+            }
+        }
+
+        pub fn move_to(&mut self, start: Nid) {
+            self.stack.clear();
+            self.stack.push(start);
+        }
+
+        pub fn reset(&mut self) {
+            self.stack.clear();
+            self.discovered.clear();
+            self.finished.clear();
+        }
+
+        pub fn is_visited(&self, node_id: &Nid) -> bool {
+            self.discovered.contains(node_id)
+        }
+
+        pub fn visited(&self) -> &HashSet<Nid> {
+            &self.discovered
+        }
+    }
+
+    impl<Nid> Walker<Nid> for DfsPostOrder<Nid>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        fn walk_next<N, E>(&mut self, graph: &Graph<Nid, N, E>) -> Option<Nid> {
+            while let Some(current) = self.stack.last().cloned() {
+                if self.discovered.insert(current.clone()) {
+                    if let Some(edges) = graph.edges_from(&current) {
+                        for (next, _) in edges.iter().rev() {
+                            if !self.discovered.contains(next) {
+                                self.stack.push(next.clone());
+                            }
+                        }
+                    }
+                } else {
+                    self.stack.pop();
+                    if self.finished.insert(current.clone()) {
+                        return Some(current);
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn forked() -> Graph<u32, (), ()> {
+            let mut graph = Graph::new();
+            graph.add_edge(0, 1, ());
+            graph.add_edge(0, 2, ());
+            graph.add_edge(5, 6, ());
+            graph
+        }
+
+        #[test]
+        fn move_to_drops_pending_nodes_in_every_walker() {
+            let graph = forked();
+
+            let mut bfs = Bfs::new(0);
+            assert_eq!(bfs.walk_next(&graph), Some(0));
+            bfs.move_to(5);
+            assert_eq!(bfs.iter(&graph).collect::<Vec<_>>(), vec![5, 6]);
+
+            let mut dfs = Dfs::new(0);
+            assert_eq!(dfs.walk_next(&graph), Some(0));
+            dfs.move_to(5);
+            assert_eq!(dfs.iter(&graph).collect::<Vec<_>>(), vec![5, 6]);
+
+            let mut post_order = DfsPostOrder::new(0);
+            assert!(post_order.walk_next(&graph).is_some());
+            post_order.move_to(5);
+            assert_eq!(post_order.iter(&graph).collect::<Vec<_>>(), vec![6, 5]);
+        }
+
+        #[test]
+        fn move_to_keeps_visited_nodes() {
+            let graph = forked();
+            let mut bfs = Bfs::new(0);
+            let first: Vec<u32> = (&mut bfs).iter(&graph).collect();
+            assert_eq!(first.len(), 3);
+            bfs.move_to(1);
+            assert_eq!(bfs.walk_next(&graph), None);
+            bfs.reset();
+            bfs.move_to(1);
+            assert_eq!(bfs.walk_next(&graph), Some(1));
+        }
+
+        fn diamond() -> Graph<u32, (), ()> {
+            let mut graph = Graph::new();
+            graph.add_edge(0, 1, ());
+            graph.add_edge(0, 2, ());
+            graph.add_edge(1, 3, ());
+            graph.add_edge(2, 3, ());
+            graph.add_edge(3, 4, ());
+            graph
+        }
+
+        #[test]
+        fn walkers_visit_in_their_orders() {
+            let graph = diamond();
+            let bfs: Vec<u32> = Bfs::new(0).iter(&graph).collect();
+            assert_eq!(bfs, vec![0, 1, 2, 3, 4]);
+            let dfs: Vec<u32> = Dfs::new(0).iter(&graph).collect();
+            assert_eq!(dfs, vec![0, 1, 3, 4, 2]);
+            let post_order: Vec<u32> = DfsPostOrder::new(0).iter(&graph).collect();
+            assert_eq!(post_order, vec![4, 3, 1, 2, 0]);
+        }
+
+        #[test]
+        fn walkers_visit_every_node_once_on_cycles() {
+            let mut graph = diamond();
+            graph.add_edge(4, 0, ());
+            graph.add_edge(3, 3, ());
+            for mut order in [
+                Bfs::new(2).iter(&graph).collect::<Vec<u32>>(),
+                Dfs::new(2).iter(&graph).collect(),
+                DfsPostOrder::new(2).iter(&graph).collect(),
+            ] {
+                order.sort();
+                assert_eq!(order, vec![0, 1, 2, 3, 4]);
+            }
+        }
+
+        #[test]
+        fn reset_forgets_visited_nodes() {
+            let graph = diamond();
+            let mut dfs = Dfs::new(3);
+            assert_eq!((&mut dfs).iter(&graph).collect::<Vec<_>>(), vec![3, 4]);
+            assert!(dfs.is_visited(&4));
+            dfs.reset();
+            assert!(dfs.visited().is_empty());
+            assert_eq!(dfs.walk_next(&graph), None);
+            dfs.move_to(3);
+            assert_eq!(dfs.iter(&graph).collect::<Vec<_>>(), vec![3, 4]);
+
+            let mut post_order = DfsPostOrder::new(1);
+            assert_eq!(post_order.walk_next(&graph), Some(4));
+            post_order.reset();
+            assert!(!post_order.is_visited(&1));
+            post_order.move_to(2);
+            assert_eq!(post_order.iter(&graph).collect::<Vec<_>>(), vec![4, 3, 2]);
+        }
+    }
+}
+
+pub mod dag {
+    use super::graph::Graph;
+    use super::*;
+    use std::cmp::{min, Reverse};
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Cycle<Nid>(pub Vec<Nid>);
+
+    pub fn toposort<Nid, N, E>(graph: &Graph<Nid, N, E>) -> Result<Vec<Nid>, Cycle<Nid>>
+    where
+        Nid: Hash + Eq + Ord + Clone,
+    {
+        let node_ids = graph.node_ids();
+        let mut in_degree: HashMap<&Nid, usize> =
+            node_ids.iter().map(|node_id| (*node_id, 0)).collect();
+        for (_, edges) in graph.iter_edges() {
+            for (to, _) in edges.iter() {
+                *in_degree.get_mut(to).unwrap() += 1;
+            }
+        }
+
+        let mut ready: BinaryHeap<Reverse<&Nid>> = in_degree
+            .iter()
+            .filter(|(_, degree)| **degree == 0)
+            .map(|(node_id, _)| Reverse(*node_id))
+            .collect();
+        let mut order: Vec<Nid> = Vec::with_capacity(node_ids.len());
+
+        while let Some(Reverse(node_id)) = ready.pop() {
+            order.push(node_id.clone());
+            if let Some(edges) = graph.edges_from(node_id) {
+                for (to, _) in edges.iter() {
+                    let degree = in_degree.get_mut(to).unwrap();
+                    *degree -= 1;
+                    if *degree == 0 {
+                        ready.push(Reverse(to));
+                    }
+                }
+            }
+        }
+
+        if order.len() == node_ids.len() {
+            return Ok(order);
+        }
+
+        // Every leftover node still has a leftover predecessor, so walking
+        // predecessors backwards has to close a cycle. Taking the smallest one
+        // keeps the reported cycle independent of hash order.
+        let mut predecessors: HashMap<&Nid, &Nid> = HashMap::new();
+        for (from, edges) in graph.iter_edges() {
+            if in_degree[from] == 0 {
+                continue;
+            }
+            for (to, _) in edges.iter() {
+                predecessors
+                    .entry(to)
+                    .and_modify(|predecessor| *predecessor = min(*predecessor, from))
+                    .or_insert(from);
+            }
+        }
+        let start = node_ids
+            .iter()
+            .filter(|node_id| in_degree[*node_id] > 0)
+            .min()
+            .unwrap();
+        let mut walked: Vec<&Nid> = vec![];
+        let mut position: HashMap<&Nid, usize> = HashMap::new();
+        let mut current: &Nid = start;
+        while !position.contains_key(current) {
+            position.insert(current, walked.len());
+            walked.push(current);
+            current = predecessors[current];
+        }
+        let mut cycle: Vec<Nid> = walked
+            .split_off(position[current])
+            .into_iter()
+            .cloned()
+            .collect();
+        cycle.reverse();
+        Err(Cycle(cycle))
+    }
+
+    pub fn is_cyclic_directed<Nid, N, E>(graph: &Graph<Nid, N, E>) -> bool
+    where
+        Nid: Hash + Eq,
+    {
+        let mut finished: HashSet<&Nid> = HashSet::new();
+        let mut on_stack: HashSet<&Nid> = HashSet::new();
+
+        for root in graph.node_ids() {
+            if finished.contains(root) {
+                continue;
+            }
+            let mut stack: Vec<(&Nid, usize)> = vec![(root, 0)];
+            on_stack.insert(root);
+            while let Some((node_id, index)) = stack.pop() {
+                let next = graph
+                    .edges_from(node_id)
+                    .and_then(|edges| edges.get(index))
+                    .map(|(to, _)| to);
+                match next {
+                    Some(to) => {
+                        stack.push((node_id, index + 1));
+                        if on_stack.contains(to) {
+                            return true;
+                        }
+                        if !finished.contains(to) {
+                            on_stack.insert(to);
+                            stack.push((to, 0));
+                        }
+                    }
+                    None => {
+                        on_stack.remove(node_id);
+                        finished.insert(node_id);
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn cycle_witness_does_not_depend_on_hash_order() {
+            for _ in 0..50 {
+                let mut graph: Graph<u32, (), ()> = Graph::new();
+                graph.add_edge(0, 1, ());
+                graph.add_edge(1, 0, ());
+                graph.add_edge(0, 2, ());
+                graph.add_edge(2, 3, ());
+                graph.add_edge(3, 0, ());
+                assert_eq!(toposort(&graph), Err(Cycle(vec![1, 0])));
+            }
+        }
+
+        fn dependencies() -> Graph<u32, (), ()> {
+            let mut graph = Graph::new();
+            graph.add_edge(5, 2, ());
+            graph.add_edge(5, 0, ());
+            graph.add_edge(4, 0, ());
+            graph.add_edge(4, 1, ());
+            graph.add_edge(2, 3, ());
+            graph.add_edge(3, 1, ());
+            graph
+        }
+
+        #[test]
+        fn toposort_takes_the_smallest_ready_node_first() {
+            let mut graph = dependencies();
+            graph.insert_node(7, ());
+            assert_eq!(toposort(&graph), Ok(vec![4, 5, 0, 2, 3, 1, 7]));
+            assert_eq!(toposort(&Graph::<u32>::new()), Ok(vec![]));
+        }
+
+        #[test]
+        fn is_cyclic_directed_finds_cycles_and_self_loops() {
+            let mut graph = dependencies();
+            assert!(!is_cyclic_directed(&graph));
+            graph.add_edge(1, 5, ());
+            assert!(is_cyclic_directed(&graph));
+            assert_eq!(toposort(&graph), Err(Cycle(vec![2, 3, 1, 5])));
+
+            let mut looped: Graph<u32, (), ()> = Graph::new();
+            looped.add_edge(0, 1, ());
+            looped.add_edge(1, 1, ());
+            assert!(is_cyclic_directed(&looped));
+            assert_eq!(toposort(&looped), Err(Cycle(vec![1])));
+        }
+    }
+}
+
+pub mod scc {
+    use super::graph::Graph;
+    use super::*;
+
+    const UNVISITED: usize = usize::MAX;
+
+    pub fn tarjan_scc<Nid, N, E>(graph: &Graph<Nid, N, E>) -> Vec<Vec<Nid>>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        let (node_ids, index) = graph.index_nodes();
+        let successors = graph.successor_lists(&index);
+        let count = node_ids.len();
+
+        let mut order = vec![UNVISITED; count];
+        let mut low_link = vec![0; count];
+        let mut on_stack = vec![false; count];
+        let mut stack: Vec<usize> = vec![];
+        let mut next_order = 0;
+        let mut components: Vec<Vec<Nid>> = vec![];
+
+        for root in 0..count {
+            if order[root] != UNVISITED {
+                continue;
+            }
+            let mut calls: Vec<(usize, usize)> = vec![(root, 0)];
+            order[root] = next_order;
+            low_link[root] = next_order;
+            next_order += 1;
+            stack.push(root);
+            on_stack[root] = true;
+
+            while let Some((node, position)) = calls.last_mut() {
+                let node = *node;
+                if let Some(&next) = successors[node].get(*position) {
+                    *position += 1;
+                    if order[next] == UNVISITED {
+                        order[next] = next_order;
+                        low_link[next] = next_order;
+                        next_order += 1;
+                        stack.push(next);
+                        on_stack[next] = true;
+                        calls.push((next, 0));
+                    } else if on_stack[next] {
+                        low_link[node] = low_link[node].min(order[next]);
+                    }
+                    continue;
+                }
+
+                calls.pop();
+                if let Some((parent, _)) = calls.last() {
+                    low_link[*parent] = low_link[*parent].min(low_link[node]);
+                }
+                if low_link[node] == order[node] {
+                    let mut component = vec![];
+                    while let Some(member) = stack.pop() {
+                        on_stack[member] = false;
+                        component.push(node_ids[member].clone());
+                        if member == node {
+                            break;
+                        }
+                    }
+                    components.push(component);
+                }
+            }
+        }
+        components
+    }
+
+    pub fn kosaraju_scc<Nid, N, E>(graph: &Graph<Nid, N, E>) -> Vec<Vec<Nid>>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        let (node_ids, index) = graph.index_nodes();
+        let successors = graph.successor_lists(&index);
+        let count = node_ids.len();
+
+        let mut predecessors: Vec<Vec<usize>> = vec![vec![]; count];
+        for (from, nexts) in successors.iter().enumerate() {
+            for &to in nexts.iter() {
+                predecessors[to].push(from);
+            }
+        }
+
+        let mut visited = vec![false; count];
+        let mut finish_order: Vec<usize> = Vec::with_capacity(count);
+        for root in 0..count {
+            if visited[root] {
+                continue;
+            }
+            visited[root] = true;
+            let mut calls: Vec<(usize, usize)> = vec![(root, 0)];
+            while let Some((node, position)) = calls.last_mut() {
+                let node = *node;
+                if let Some(&next) = successors[node].get(*position) {
+                    *position += 1;
+                    if !visited[next] {
+                        visited[next] = true;
+                        calls.push((next, 0));
+                    }
+                } else {
+                    calls.pop();
+                    finish_order.push(node);
+                }
+            }
+        }
+
+        let mut assigned = vec![false; count];
+        let mut components: Vec<Vec<Nid>> = vec![];
+        for &root in finish_order.iter().rev() {
+            if assigned[root] {
+                continue;
+            }
+            assigned[root] = true;
+            let mut stack = vec![root];
+            let mut component = vec![];
+            while let Some(node) = stack.pop() {
+                component.push(node_ids[node].clone());
+                for &previous in predecessors[node].iter() {
+                    if !assigned[previous] {
+                        assigned[previous] = true;
+                        stack.push(previous);
+                    }
+                }
+            }
+            components.push(component);
+        }
+        // Components come out sources first, flip them to match Tarjan.
+        components.reverse();
+        components
+    }
+
+    pub fn condensation<Nid, N, E>(
+        graph: &Graph<Nid, N, E>,
+        keep_parallel_edges: bool,
+    ) -> Graph<usize, Vec<Nid>, Vec<E>>
+    where
+        Nid: Hash + Eq + Clone,
+        E: Clone,
+    {
+        let components = tarjan_scc(graph);
+        let mut component_of: HashMap<&Nid, usize> = HashMap::new();
+        for (component, members) in components.iter().enumerate() {
+            for member in members.iter() {
+                component_of.insert(member, component);
+            }
+        }
+
+        // Edges go in component by component, member by member, so their order
+        // follows the components and `Graph::edges_from`.
+        let mut condensed: Graph<usize, Vec<Nid>, Vec<E>> = Graph::new();
+        let mut merged: Vec<(usize, usize, Vec<E>)> = vec![];
+        let mut merged_index: HashMap<(usize, usize), usize> = HashMap::new();
+        for (source, members) in components.iter().enumerate() {
+            for member in members.iter() {
+                for (to, edge) in graph.edges_from(member).into_iter().flatten() {
+                    let target = component_of[to];
+                    if source == target {
+                        continue;
+                    }
+                    if keep_parallel_edges {
+                        condensed.add_edge(source, target, vec![edge.clone()]);
+                        continue;
+                    }
+                    let position = *merged_index.entry((source, target)).or_insert_with(|| {
+                        merged.push((source, target, vec![]));
+                        merged.len() - 1
+                    });
+                    merged[position].2.push(edge.clone());
+                }
+            }
+        }
+        for (source, target, edges) in merged {
+            condensed.add_edge(source, target, edges);
+        }
+        for (component, members) in components.into_iter().enumerate() {
+            condensed.insert_node(component, members);
+        }
+        condensed
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn three_layers() -> Graph<u32, (), char> {
+            let mut graph = Graph::new();
+            graph.add_edge(0, 1, 'a');
+            graph.add_edge(1, 2, 'b');
+            graph.add_edge(2, 0, 'c');
+            graph.add_edge(2, 3, 'd');
+            graph.add_edge(1, 4, 'e');
+            graph.add_edge(3, 4, 'f');
+            graph.add_edge(4, 3, 'g');
+            graph.add_edge(4, 5, 'h');
+            graph.add_edge(1, 5, 'i');
+            graph.add_edge(6, 6, 'j');
+            graph
+        }
+
+        fn sorted(mut components: Vec<Vec<u32>>) -> Vec<Vec<u32>> {
+            for component in components.iter_mut() {
+                component.sort();
+            }
+            components.sort();
+            components
+        }
+
+        fn assert_sinks_first(graph: &Graph<u32, (), char>, components: &[Vec<u32>]) {
+            let position = |node_id: &u32| {
+                components
+                    .iter()
+                    .position(|component| component.contains(node_id))
+                    .unwrap()
+            };
+            for (from, edges) in graph.iter_edges() {
+                for (to, _) in edges.iter() {
+                    assert!(position(from) >= position(to));
+                }
+            }
+        }
+
+        #[test]
+        fn tarjan_and_kosaraju_find_the_same_components() {
+            let graph = three_layers();
+            let expected = vec![vec![0, 1, 2], vec![3, 4], vec![5], vec![6]];
+            for components in [tarjan_scc(&graph), kosaraju_scc(&graph)] {
+                assert_sinks_first(&graph, &components);
+                assert_eq!(sorted(components), expected);
+            }
+            assert!(tarjan_scc(&Graph::<u32>::new()).is_empty());
+            assert!(kosaraju_scc(&Graph::<u32>::new()).is_empty());
+        }
+
+        #[test]
+        fn long_cycles_do_not_overflow_the_stack() {
+            let mut graph: Graph<u32, (), ()> = Graph::new();
+            for node_id in 0..100_000 {
+                graph.add_edge(node_id, (node_id + 1) % 100_000, ());
+            }
+            assert_eq!(tarjan_scc(&graph).len(), 1);
+            assert_eq!(kosaraju_scc(&graph).len(), 1);
+        }
+
+        fn component_of(condensed: &Graph<usize, Vec<u32>, Vec<char>>, member: u32) -> usize {
+            *condensed
+                .iter_nodes()
+                .find(|(_, members)| members.contains(&member))
+                .unwrap()
+                .0
+        }
+
+        #[test]
+        fn condensation_merges_edges_between_components() {
+            let condensed = condensation(&three_layers(), false);
+            assert_eq!(condensed.node_ids().len(), 4);
+            let (a, b, c, d) = (
+                component_of(&condensed, 0),
+                component_of(&condensed, 3),
+                component_of(&condensed, 5),
+                component_of(&condensed, 6),
+            );
+            let mut members = condensed.get_node(&a).unwrap().clone();
+            members.sort();
+            assert_eq!(members, vec![0, 1, 2]);
+
+            let mut between = condensed.get_edge(&a, &b).unwrap().clone();
+            between.sort();
+            assert_eq!(between, vec!['d', 'e']);
+            assert_eq!(condensed.edge_count(&a, &b), 1);
+            assert_eq!(condensed.edges_from(&a).unwrap()[0].0, b);
+            assert_eq!(condensed.get_edge(&a, &c), Some(&vec!['i']));
+            assert_eq!(condensed.get_edge(&b, &c), Some(&vec!['h']));
+            // Edges inside a component, self-loops included, disappear.
+            assert_eq!(condensed.edge_count(&a, &a), 0);
+            assert_eq!(condensed.edge_count(&d, &d), 0);
+            assert!(!super::super::dag::is_cyclic_directed(&condensed));
+        }
+
+        #[test]
+        fn condensation_can_keep_parallel_edges() {
+            let condensed = condensation(&three_layers(), true);
+            let (a, b) = (component_of(&condensed, 0), component_of(&condensed, 3));
+            let mut between: Vec<char> = condensed
+                .edges_from_to(&a, &b)
+                .unwrap()
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect();
+            between.sort();
+            assert_eq!(between, vec!['d', 'e']);
+            assert_eq!(condensed.edge_count(&a, &b), 2);
+        }
+
+        #[test]
+        fn condensation_orders_edges_by_component_then_member() {
+            let mut graph = three_layers();
+            graph.add_edge(2, 4, 'k');
+            for keep_parallel_edges in [false, true] {
+                let condensed = condensation(&graph, keep_parallel_edges);
+                let (a, b) = (component_of(&condensed, 0), component_of(&condensed, 3));
+                let mut expected = vec![];
+                for member in condensed.get_node(&a).unwrap() {
+                    for (to, edge) in graph.edges_from(member).unwrap() {
+                        if component_of(&condensed, *to) == b {
+                            expected.push(*edge);
+                        }
+                    }
+                }
+                let between: Vec<char> = condensed
+                    .edges_from_to(&a, &b)
+                    .unwrap()
+                    .into_iter()
+                    .flatten()
+                    .cloned()
+                    .collect();
+                assert_eq!(between, expected);
+                let position = |label| between.iter().position(|edge| *edge == label);
+                assert_eq!(position('d').unwrap() + 1, position('k').unwrap());
+            }
+        }
+    }
+}
+
+pub mod connectivity {
+    use super::graph::Graph;
+    use super::*;
+
+    #[derive(Clone, Debug)]
+    pub struct UnionFind<T> {
+        index: HashMap<T, usize>,
+        elements: Vec<T>,
+        parent: Vec<usize>,
+        rank: Vec<u8>,
+        set_count: usize,
+    }
+
+    impl<T> Default for UnionFind<T>
+    where
+        T: Hash + Eq + Clone,
+    {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<T> UnionFind<T>
+    where
+        T: Hash + Eq + Clone,
+    {
+        pub fn new() -> Self {
+            UnionFind {
+                index: HashMap::new(),
+                elements: vec![],
+                parent: vec![],
+                rank: vec![],
+                set_count: 0,
+            }
+        }
+
+        pub fn from_graph<N, E>(graph: &Graph<T, N, E>) -> Self {
+            let mut union_find = Self::new();
+            for node_id in graph.node_ids() {
+                union_find.make_set(node_id.clone());
+            }
+            for (from, edges) in graph.iter_edges() {
+                for (to, _) in edges.iter() {
+                    union_find.union(from.clone(), to.clone());
+                }
+            }
+            union_find
+        }
+
+        pub fn make_set(&mut self, element: T) -> bool {
+            if self.index.contains_key(&element) {
+                return false;
+            }
+            let position = self.elements.len();
+            self.index.insert(element.clone(), position);
+            self.elements.push(element);
+            self.parent.push(position);
+            self.rank.push(0);
+            self.set_count += 1;
+            true
+        }
+
+        fn root(&mut self, mut position: usize) -> usize {
+            while self.parent[position] != position {
+                self.parent[position] = self.parent[self.parent[position]];
+                position = self.parent[position];
+            }
+            position
+        }
+
+        pub fn find(&mut self, element: &T) -> Option<&T> {
+            let position = *self.index.get(element)?;
+            let root = self.root(position);
+            Some(&self.elements[root])
+        }
+
+        pub fn union(&mut self, a: T, b: T) -> bool {
+            self.make_set(a.clone());
+            self.make_set(b.clone());
+            let root_a = self.root(self.index[&a]);
+            let root_b = self.root(self.index[&b]);
+            if root_a == root_b {
+                return false;
+            }
+            match self.rank[root_a].cmp(&self.rank[root_b]) {
+                std::cmp::Ordering::Less => self.parent[root_a] = root_b,
+                std::cmp::Ordering::Greater => self.parent[root_b] = root_a,
+                std::cmp::Ordering::Equal => {
+                    self.parent[root_b] = root_a;
+                    self.rank[root_a] += 1;
+                }
+            }
+            self.set_count -= 1;
+            true
+        }
+
+        pub fn add_edge(&mut self, from: T, to: T) -> bool {
+            self.union(from, to)
+        }
+
+        pub fn is_connected(&mut self, a: &T, b: &T) -> bool {
+            match (self.index.get(a), self.index.get(b)) {
+                (Some(&a), Some(&b)) => self.root(a) == self.root(b),
+                _ => false,
+            }
+        }
+
+        pub fn contains(&self, element: &T) -> bool {
+            self.index.contains_key(element)
+        }
+
+        pub fn len(&self) -> usize {
+            self.elements.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.elements.is_empty()
+        }
+
+        pub fn set_count(&self) -> usize {
+            self.set_count
+        }
+
+        pub fn sets(&mut self) -> Vec<Vec<T>> {
+            let mut groups: HashMap<usize, usize> = HashMap::new();
+            let mut sets: Vec<Vec<T>> = vec![];
+            for position in 0..self.elements.len() {
+                let root = self.root(position);
+                let group = *groups.entry(root).or_insert_with(|| {
+                    sets.push(vec![]);
+                    sets.len() - 1
+                });
+                sets[group].push(self.elements[position].clone());
+            }
+            sets
+        }
+    }
+
+    // A graph together with the disjoint sets of its nodes, kept up to date as
+    // nodes and edges are added so queries never rebuild them.
+    #[derive(Clone, Debug)]
+    pub struct Connectivity<Nid, N = (), E = ()> {
+        graph: Graph<Nid, N, E>,
+        components: UnionFind<Nid>,
+    }
+
+    impl<Nid, N, E> Default for Connectivity<Nid, N, E>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<Nid, N, E> Connectivity<Nid, N, E>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        pub fn new() -> Self {
+            Connectivity {
+                graph: Graph::new(),
+                components: UnionFind::new(),
+            }
+        }
+
+        pub fn from_graph(graph: Graph<Nid, N, E>) -> Self {
+            Connectivity {
+                components: UnionFind::from_graph(&graph),
+                graph,
+            }
+        }
+
+        pub fn graph(&self) -> &Graph<Nid, N, E> {
+            &self.graph
+        }
+
+        pub fn into_graph(self) -> Graph<Nid, N, E> {
+            self.graph
+        }
+
+        pub fn insert_node(&mut self, node_id: Nid, node: N) -> Option<N> {
+            self.components.make_set(node_id.clone());
+            self.graph.insert_node(node_id, node)
+        }
+
+        pub fn add_edge(&mut self, from: Nid, to: Nid, edge: E) {
+            self.components.union(from.clone(), to.clone());
+            self.graph.add_edge(from, to, edge);
+        }
+
+        pub fn push_undirected_edge(&mut self, from: Nid, to: Nid, edge: E)
+        where
+            E: PartialEq + Clone,
+        {
+            self.components.union(from.clone(), to.clone());
+            self.graph.push_undirected_edge(from, to, edge);
+        }
+
+        pub fn connected_components(&self) -> usize {
+            self.components.set_count()
+        }
+
+        pub fn is_connected(&mut self, a: &Nid, b: &Nid) -> bool {
+            self.components.is_connected(a, b)
+        }
+
+        pub fn weakly_connected_components(&mut self) -> Vec<Vec<Nid>> {
+            self.components.sets()
+        }
+    }
+
+    // The functions below rebuild the disjoint sets on every call, `Connectivity`
+    // keeps them around for repeated queries on a growing graph.
+    pub fn weakly_connected_components<Nid, N, E>(graph: &Graph<Nid, N, E>) -> Vec<Vec<Nid>>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        UnionFind::from_graph(graph).sets()
+    }
+
+    pub fn connected_components<Nid, N, E>(graph: &Graph<Nid, N, E>) -> usize
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        UnionFind::from_graph(graph).set_count()
+    }
+
+    pub fn is_connected<Nid, N, E>(graph: &Graph<Nid, N, E>, a: &Nid, b: &Nid) -> bool
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        UnionFind::from_graph(graph).is_connected(a, b)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn union_find_merges_sets() {
+            let mut union_find: UnionFind<&str> = UnionFind::default();
+            assert!(union_find.is_empty());
+            assert!(union_find.make_set("a"));
+            assert!(!union_find.make_set("a"));
+            assert!(union_find.union("a", "b"));
+            assert!(union_find.union("c", "d"));
+            assert!(!union_find.union("b", "a"));
+            assert_eq!(union_find.len(), 4);
+            assert_eq!(union_find.set_count(), 2);
+            assert!(union_find.is_connected(&"a", &"b"));
+            assert!(!union_find.is_connected(&"a", &"c"));
+            assert!(!union_find.is_connected(&"a", &"z"));
+            assert_eq!(union_find.find(&"z"), None);
+
+            assert!(union_find.add_edge("b", "d"));
+            assert_eq!(union_find.set_count(), 1);
+            let root = *union_find.find(&"a").unwrap();
+            assert_eq!(union_find.find(&"c"), Some(&root));
+        }
+
+        #[test]
+        fn components_ignore_edge_direction() {
+            let mut graph: Graph<u32, (), ()> = Graph::new();
+            graph.add_edge(0, 1, ());
+            graph.add_edge(2, 1, ());
+            graph.add_edge(3, 4, ());
+            graph.insert_node(5, ());
+
+            assert_eq!(connected_components(&graph), 3);
+            assert!(is_connected(&graph, &0, &2));
+            assert!(!is_connected(&graph, &0, &3));
+            assert!(!is_connected(&graph, &0, &9));
+
+            let mut components = weakly_connected_components(&graph);
+            for component in components.iter_mut() {
+                component.sort();
+            }
+            components.sort();
+            assert_eq!(components, vec![vec![0, 1, 2], vec![3, 4], vec![5]]);
+            assert_eq!(connected_components(&Graph::<u32>::new()), 0);
+        }
+
+        #[test]
+        fn connectivity_follows_added_edges() {
+            let mut graph: Graph<u32, (), ()> = Graph::new();
+            graph.add_edge(0, 1, ());
+            graph.insert_node(5, ());
+            let mut connectivity = Connectivity::from_graph(graph);
+            assert_eq!(connectivity.connected_components(), 2);
+
+            connectivity.add_edge(2, 1, ());
+            connectivity.push_undirected_edge(3, 4, ());
+            connectivity.insert_node(6, ());
+            assert_eq!(connectivity.connected_components(), 4);
+            assert!(connectivity.is_connected(&0, &2));
+            assert!(!connectivity.is_connected(&0, &3));
+
+            connectivity.add_edge(4, 5, ());
+            assert_eq!(connectivity.connected_components(), 3);
+            assert!(connectivity.is_connected(&3, &5));
+            assert_eq!(
+                connectivity.connected_components(),
+                connected_components(connectivity.graph())
+            );
+
+            let mut components = connectivity.weakly_connected_components();
+            for component in components.iter_mut() {
+                component.sort();
+            }
+            components.sort();
+            assert_eq!(components, vec![vec![0, 1, 2], vec![3, 4, 5], vec![6]]);
+            assert_eq!(connectivity.into_graph().edge_count(&3, &4), 1);
+        }
+    }
+}
+
+pub mod spanning_tree {
+    use super::connectivity::UnionFind;
+    use super::graph::Graph;
+    use super::shortest_path::{Measure, MinScored};
+    use super::*;
+    use std::cmp::Ordering;
+
+    #[derive(Clone, Debug)]
+    pub struct SpanningForest<Nid, K> {
+        pub nodes: Vec<Nid>,
+        pub edges: Vec<(Nid, Nid, K)>,
+        pub total: K,
+    }
+
+    impl<Nid, K> SpanningForest<Nid, K>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure,
+    {
+        pub fn tree_count(&self) -> usize {
+            self.nodes.len() - self.edges.len()
+        }
+
+        pub fn to_graph(&self) -> Graph<Nid, (), K> {
+            let mut graph = Graph::new();
+            for node_id in self.nodes.iter() {
+                graph.insert_node(node_id.clone(), ());
+            }
+            for (from, to, weight) in self.edges.iter() {
+                graph.add_edge(from.clone(), to.clone(), *weight);
+                graph.add_edge(to.clone(), from.clone(), *weight);
+            }
+            graph
+        }
+    }
+
+    pub fn min_spanning_tree<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        edge_cost: F,
+    ) -> SpanningForest<Nid, K>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure,
+        F: FnMut(&E) -> K,
+    {
+        kruskal(graph, edge_cost)
+    }
+
+    pub fn kruskal<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        mut edge_cost: F,
+    ) -> SpanningForest<Nid, K>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure,
+        F: FnMut(&E) -> K,
+    {
+        let node_ids = graph.node_ids();
+        let mut candidates: Vec<(&Nid, &Nid, K)> = vec![];
+        for (from, edges) in graph.iter_edges() {
+            for (to, edge) in edges.iter() {
+                if from != to {
+                    candidates.push((from, to, edge_cost(edge)));
+                }
+            }
+        }
+        candidates.sort_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(Ordering::Equal));
+
+        let mut components: UnionFind<&Nid> = UnionFind::new();
+        for node_id in node_ids.iter() {
+            components.make_set(*node_id);
+        }
+        let mut total = K::default();
+        let mut chosen = vec![];
+        for (from, to, weight) in candidates {
+            if components.union(from, to) {
+                total = total + weight;
+                chosen.push((from.clone(), to.clone(), weight));
+            }
+        }
+
+        SpanningForest {
+            nodes: node_ids.into_iter().cloned().collect(),
+            edges: chosen,
+            total,
+        }
+    }
+
+    pub fn prim<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        mut edge_cost: F,
+    ) -> SpanningForest<Nid, K>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure,
+        F: FnMut(&E) -> K,
+    {
+        let (node_ids, index) = graph.index_nodes();
+        let mut neighbours: Vec<Vec<(usize, K)>> = vec![vec![]; node_ids.len()];
+        for (from, edges) in graph.iter_edges() {
+            let from = index[from];
+            for (to, edge) in edges.iter() {
+                let to = index[to];
+                let weight = edge_cost(edge);
+                neighbours[from].push((to, weight));
+                neighbours[to].push((from, weight));
+            }
+        }
+
+        let mut in_tree = vec![false; node_ids.len()];
+        let mut total = K::default();
+        let mut chosen = vec![];
+        let mut heap = BinaryHeap::new();
+        for root in 0..node_ids.len() {
+            if in_tree[root] {
+                continue;
+            }
+            in_tree[root] = true;
+            for &(to, weight) in neighbours[root].iter() {
+                heap.push(MinScored(weight, (root, to)));
+            }
+            while let Some(MinScored(weight, (from, to))) = heap.pop() {
+                if in_tree[to] {
+                    continue;
+                }
+                in_tree[to] = true;
+                total = total + weight;
+                chosen.push((node_ids[from].clone(), node_ids[to].clone(), weight));
+                for &(next, weight) in neighbours[to].iter() {
+                    if !in_tree[next] {
+                        heap.push(MinScored(weight, (to, next)));
+                    }
+                }
+            }
+        }
+
+        SpanningForest {
+            nodes: node_ids.into_iter().cloned().collect(),
+            edges: chosen,
+            total,
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::super::connectivity;
+        use super::*;
+
+        fn two_parts() -> Graph<char, (), u32> {
+            let mut graph = Graph::new();
+            for (from, to, weight) in [
+                ('a', 'b', 7),
+                ('a', 'd', 5),
+                ('b', 'c', 8),
+                ('b', 'd', 9),
+                ('b', 'e', 7),
+                ('c', 'e', 5),
+                ('d', 'e', 15),
+                ('d', 'f', 6),
+                ('e', 'f', 8),
+                ('e', 'g', 9),
+                ('f', 'g', 11),
+                ('x', 'y', 2),
+                ('y', 'y', 0),
+            ] {
+                graph.add_edge(from, to, weight);
+            }
+            graph.insert_node('z', ());
+            graph
+        }
+
+        #[test]
+        fn kruskal_and_prim_build_minimum_forests() {
+            let graph = two_parts();
+            for forest in [
+                kruskal(&graph, |weight| *weight),
+                prim(&graph, |weight| *weight),
+                min_spanning_tree(&graph, |weight| *weight),
+            ] {
+                assert_eq!(forest.total, 41);
+                assert_eq!(forest.nodes.len(), 10);
+                assert_eq!(forest.edges.len(), 7);
+                assert_eq!(forest.tree_count(), 3);
+                assert_eq!(
+                    forest
+                        .edges
+                        .iter()
+                        .map(|(_, _, weight)| weight)
+                        .sum::<u32>(),
+                    41
+                );
+
+                let tree = forest.to_graph();
+                assert!(connectivity::is_connected(&tree, &'a', &'g'));
+                assert!(!connectivity::is_connected(&tree, &'a', &'x'));
+                assert!(tree.contains(&'z'));
+                assert_eq!(tree.edge_count(&'y', &'y'), 0);
+            }
+        }
+
+        #[test]
+        fn spanning_forests_accept_float_weights() {
+            let mut graph: Graph<u32, (), f64> = Graph::new();
+            graph.add_edge(0, 1, 0.5);
+            graph.add_edge(1, 2, 0.25);
+            graph.add_edge(0, 2, 1.0);
+            assert_eq!(kruskal(&graph, |weight| *weight).total, 0.75);
+            assert_eq!(prim(&graph, |weight| *weight).total, 0.75);
+            assert_eq!(
+                prim(&Graph::<u32, (), f64>::new(), |weight| *weight).tree_count(),
+                0
+            );
+        }
+    }
+}
+
+pub mod flow {
+    use super::graph::Graph;
+    use super::shortest_path::{Measure, MinScored};
+    use super::*;
+    use std::ops::{Mul, Neg, Sub};
+
+    const UNREACHED: usize = usize::MAX;
+
+    fn min<K: PartialOrd>(a: K, b: K) -> K {
+        if b < a {
+            b
+        } else {
+            a
+        }
+    }
+
+    // Arcs are stored in pairs, arc `a ^ 1` is the reverse of arc `a`, so the flow
+    // pushed over an arc is always the residual capacity of its reverse.
+    struct Residual<'a, Nid, K> {
+        node_ids: Vec<&'a Nid>,
+        index: HashMap<&'a Nid, usize>,
+        arcs_from: Vec<Vec<usize>>,
+        head: Vec<usize>,
+        residual: Vec<K>,
+        origin: Vec<(&'a Nid, usize)>,
+    }
+
+    impl<'a, Nid, K> Residual<'a, Nid, K>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure + Sub<Output = K>,
+    {
+        fn from_graph<N, E, F>(graph: &'a Graph<Nid, N, E>, mut capacity: F) -> Self
+        where
+            F: FnMut(&E) -> K,
+        {
+            let (node_ids, index) = graph.index_nodes();
+            let mut residual = Residual {
+                arcs_from: vec![vec![]; node_ids.len()],
+                node_ids,
+                index,
+                head: vec![],
+                residual: vec![],
+                origin: vec![],
+            };
+            for (from, edges) in graph.iter_edges() {
+                for (position, (to, edge)) in edges.iter().enumerate() {
+                    let (u, v) = (residual.index[from], residual.index[to]);
+                    residual.add_arc(u, v, capacity(edge));
+                    residual.origin.push((from, position));
+                }
+            }
+            residual
+        }
+
+        fn add_arc(&mut self, from: usize, to: usize, capacity: K) -> usize {
+            let arc = self.head.len();
+            self.arcs_from[from].push(arc);
+            self.head.push(to);
+            self.residual.push(capacity);
+            self.arcs_from[to].push(arc + 1);
+            self.head.push(from);
+            self.residual.push(K::default());
+            arc
+        }
+
+        fn add_node(&mut self) -> usize {
+            self.arcs_from.push(vec![]);
+            self.arcs_from.len() - 1
+        }
+
+        fn tail(&self, arc: usize) -> usize {
+            self.head[arc ^ 1]
+        }
+
+        fn reachable_from(&self, source: usize) -> Vec<bool> {
+            let mut reached = vec![false; self.arcs_from.len()];
+            let mut queue = VecDeque::from([source]);
+            reached[source] = true;
+            while let Some(node) = queue.pop_front() {
+                for &arc in self.arcs_from[node].iter() {
+                    let next = self.head[arc];
+                    if !reached[next] && self.residual[arc] > K::default() {
+                        reached[next] = true;
+                        queue.push_back(next);
+                    }
+                }
+            }
+            reached
+        }
+
+        // Exact residual distances to the sink, nodes that can no longer reach it
+        // drain back towards the source instead.
+        fn global_relabel(&self, source: usize, sink: usize, height: &mut [usize]) {
+            let count = self.node_ids.len();
+            height.fill(2 * count);
+            for (root, base) in [(sink, 0), (source, count)] {
+                height[root] = base;
+                let mut queue = VecDeque::from([root]);
+                while let Some(node) = queue.pop_front() {
+                    for &arc in self.arcs_from[node].iter() {
+                        let previous = self.head[arc];
+                        if height[previous] == 2 * count
+                            && previous != source
+                            && self.residual[arc ^ 1] > K::default()
+                        {
+                            height[previous] = height[node] + 1;
+                            queue.push_back(previous);
+                        }
+                    }
+                }
+            }
+        }
+
+        fn augment(&mut self, arc: usize, amount: K) {
+            self.residual[arc] = self.residual[arc] - amount;
+            self.residual[arc ^ 1] = self.residual[arc ^ 1] + amount;
+        }
+
+        fn edge_flows(&self) -> HashMap<(Nid, usize), K> {
+            self.origin
+                .iter()
+                .enumerate()
+                .map(|(edge, (from, position))| {
+                    (((*from).clone(), *position), self.residual[2 * edge + 1])
+                })
+                .collect()
+        }
+
+        // Dinic's phases, each saturating every shortest augmenting path at once.
+        fn blocking_flows(&mut self, source: usize, sink: usize) -> K {
+            let count = self.arcs_from.len();
+            let zero = K::default();
+            let mut value = zero;
+            loop {
+                let mut level = vec![UNREACHED; count];
+                level[source] = 0;
+                let mut queue = VecDeque::from([source]);
+                while let Some(node) = queue.pop_front() {
+                    for &arc in self.arcs_from[node].iter() {
+                        let next = self.head[arc];
+                        if level[next] == UNREACHED && self.residual[arc] > zero {
+                            level[next] = level[node] + 1;
+                            queue.push_back(next);
+                        }
+                    }
+                }
+                if level[sink] == UNREACHED {
+                    break;
+                }
+
+                let mut current_arc = vec![0; count];
+                let mut path: Vec<usize> = vec![];
+                let mut node = source;
+                loop {
+                    if node == sink {
+                        let bottleneck = path
+                            .iter()
+                            .map(|&arc| self.residual[arc])
+                            .fold(self.residual[path[0]], min);
+                        for &arc in path.iter() {
+                            self.augment(arc, bottleneck);
+                        }
+                        value = value + bottleneck;
+                        let saturated = path
+                            .iter()
+                            .position(|&arc| self.residual[arc] <= zero)
+                            .unwrap_or(0);
+                        node = self.tail(path[saturated]);
+                        path.truncate(saturated);
+                        continue;
+                    }
+
+                    let mut advanced = false;
+                    while let Some(&arc) = self.arcs_from[node].get(current_arc[node]) {
+                        let next = self.head[arc];
+                        if self.residual[arc] > zero && level[next] == level[node] + 1 {
+                            path.push(arc);
+                            node = next;
+                            advanced = true;
+                            break;
+                        }
+                        current_arc[node] += 1;
+                    }
+                    if advanced {
+                        continue;
+                    }
+
+                    // Dead end, nothing in this phase can pass through `node` anymore.
+                    level[node] = UNREACHED;
+                    match path.pop() {
+                        Some(arc) => {
+                            node = self.tail(arc);
+                            current_arc[node] += 1;
+                        }
+                        None => break,
+                    }
+                }
+            }
+            value
+        }
+
+        // Result for a missing source or sink, or a sink equal to the source.
+        fn without_flow(self, source: &Nid) -> MaxFlow<Nid, K> {
+            match self.index.get(source) {
+                Some(&source) => self.into_max_flow(source, K::default()),
+                None => MaxFlow {
+                    value: K::default(),
+                    edge_flows: self.edge_flows(),
+                    source_side: HashSet::new(),
+                },
+            }
+        }
+
+        fn into_max_flow(self, source: usize, value: K) -> MaxFlow<Nid, K> {
+            let reached = self.reachable_from(source);
+            let edge_flows = self.edge_flows();
+            let source_side = self
+                .node_ids
+                .iter()
+                .zip(reached)
+                .filter(|(_, reached)| *reached)
+                .map(|(node_id, _)| (*node_id).clone())
+                .collect();
+            MaxFlow {
+                value,
+                edge_flows,
+                source_side,
+            }
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct MaxFlow<Nid, K> {
+        pub value: K,
+        // Keyed by the edge's source node and its position in `Graph::edges_from`.
+        pub edge_flows: HashMap<(Nid, usize), K>,
+        pub source_side: HashSet<Nid>,
+    }
+
+    impl<Nid, K> MaxFlow<Nid, K>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure,
+    {
+        pub fn flow_on(&self, from: &Nid, position: usize) -> K {
+            self.edge_flows
+                .get(&(from.clone(), position))
+                .copied()
+                .unwrap_or_default()
+        }
+
+        pub fn min_cut_edges<N, E>(&self, graph: &Graph<Nid, N, E>) -> Vec<(Nid, usize)> {
+            let mut cut = vec![];
+            for (from, edges) in graph.iter_edges() {
+                if !self.source_side.contains(from) {
+                    continue;
+                }
+                for (position, (to, _)) in edges.iter().enumerate() {
+                    if !self.source_side.contains(to) {
+                        cut.push((from.clone(), position));
+                    }
+                }
+            }
+            cut
+        }
+    }
+
+    fn endpoints<Nid, K>(
+        residual: &Residual<'_, Nid, K>,
+        source: &Nid,
+        sink: &Nid,
+    ) -> Option<(usize, usize)>
+    where
+        Nid: Hash + Eq,
+    {
+        let source = *residual.index.get(source)?;
+        let sink = *residual.index.get(sink)?;
+        if source == sink {
+            None
+        } else {
+            Some((source, sink))
+        }
+    }
+
+    pub fn max_flow<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        source: &Nid,
+        sink: &Nid,
+        capacity: F,
+    ) -> MaxFlow<Nid, K>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure + Sub<Output = K>,
+        F: FnMut(&E) -> K,
+    {
+        dinic(graph, source, sink, capacity)
+    }
+
+    pub fn edmonds_karp<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        source: &Nid,
+        sink: &Nid,
+        capacity: F,
+    ) -> MaxFlow<Nid, K>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure + Sub<Output = K>,
+        F: FnMut(&E) -> K,
+    {
+        let mut network = Residual::from_graph(graph, capacity);
+        let zero = K::default();
+        let (source, sink) = match endpoints(&network, source, sink) {
+            Some(endpoints) => endpoints,
+            None => return network.without_flow(source),
+        };
+
+        let mut value = zero;
+        loop {
+            let mut parent_arc = vec![UNREACHED; network.node_ids.len()];
+            let mut queue = VecDeque::from([source]);
+            while let Some(node) = queue.pop_front() {
+                if node == sink {
+                    break;
+                }
+                for &arc in network.arcs_from[node].iter() {
+                    let next = network.head[arc];
+                    if next != source
+                        && parent_arc[next] == UNREACHED
+                        && network.residual[arc] > zero
+                    {
+                        parent_arc[next] = arc;
+                        queue.push_back(next);
+                    }
+                }
+            }
+            if parent_arc[sink] == UNREACHED {
+                break;
+            }
+
+            let mut bottleneck = network.residual[parent_arc[sink]];
+            let mut node = sink;
+            while node != source {
+                bottleneck = min(bottleneck, network.residual[parent_arc[node]]);
+                node = network.tail(parent_arc[node]);
+            }
+            let mut node = sink;
+            while node != source {
+                network.augment(parent_arc[node], bottleneck);
+                node = network.tail(parent_arc[node]);
+            }
+            value = value + bottleneck;
+        }
+
+        network.into_max_flow(source, value)
+    }
+
+    pub fn dinic<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        source: &Nid,
+        sink: &Nid,
+        capacity: F,
+    ) -> MaxFlow<Nid, K>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure + Sub<Output = K>,
+        F: FnMut(&E) -> K,
+    {
+        let mut network = Residual::from_graph(graph, capacity);
+        let (source, sink) = match endpoints(&network, source, sink) {
+            Some(endpoints) => endpoints,
+            None => return network.without_flow(source),
+        };
+        let value = network.blocking_flows(source, sink);
+        network.into_max_flow(source, value)
+    }
+
+    pub fn push_relabel<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        source: &Nid,
+        sink: &Nid,
+        capacity: F,
+    ) -> MaxFlow<Nid, K>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure + Sub<Output = K>,
+        F: FnMut(&E) -> K,
+    {
+        let mut network = Residual::from_graph(graph, capacity);
+        let zero = K::default();
+        let (source, sink) = match endpoints(&network, source, sink) {
+            Some(endpoints) => endpoints,
+            None => return network.without_flow(source),
+        };
+        let count = network.node_ids.len();
+
+        let mut height = vec![0; count];
+        let mut excess = vec![zero; count];
+        let mut current_arc = vec![0; count];
+        let mut active: VecDeque<usize> = VecDeque::new();
+        let mut relabels = 0;
+
+        for position in 0..network.arcs_from[source].len() {
+            let arc = network.arcs_from[source][position];
+            let amount = network.residual[arc];
+            if amount > zero {
+                let next = network.head[arc];
+                let was_idle = excess[next] <= zero;
+                network.augment(arc, amount);
+                // The source's own excess is never read, leaving it at zero keeps
+                // unsigned capacities from underflowing.
+                excess[next] = excess[next] + amount;
+                if was_idle && next != sink && next != source {
+                    active.push_back(next);
+                }
+            }
+        }
+
+        network.global_relabel(source, sink, &mut height);
+
+        while let Some(node) = active.pop_front() {
+            while excess[node] > zero {
+                let arc = match network.arcs_from[node].get(current_arc[node]) {
+                    Some(&arc) => arc,
+                    None if relabels >= count => {
+                        network.global_relabel(source, sink, &mut height);
+                        current_arc.fill(0);
+                        relabels = 0;
+                        continue;
+                    }
+                    None => {
+                        relabels += 1;
+                        height[node] = network.arcs_from[node]
+                            .iter()
+                            .filter(|&&arc| network.residual[arc] > zero)
+                            .map(|&arc| height[network.head[arc]] + 1)
+                            .min()
+                            .unwrap_or(2 * count);
+                        current_arc[node] = 0;
+                        continue;
+                    }
+                };
+                let next = network.head[arc];
+                if network.residual[arc] > zero && height[node] == height[next] + 1 {
+                    let amount = min(excess[node], network.residual[arc]);
+                    network.augment(arc, amount);
+                    excess[node] = excess[node] - amount;
+                    let was_idle = excess[next] <= zero;
+                    excess[next] = excess[next] + amount;
+                    if was_idle && next != source && next != sink {
+                        active.push_back(next);
+                    }
+                } else {
+                    current_arc[node] += 1;
+                }
+            }
+        }
+
+        network.into_max_flow(source, excess[sink])
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct MinCostFlow<Nid, K> {
+        pub value: K,
+        pub cost: K,
+        // Keyed by the edge's source node and its position in `Graph::edges_from`.
+        pub edge_flows: HashMap<(Nid, usize), K>,
+    }
+
+    impl<Nid, K> MinCostFlow<Nid, K>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure,
+    {
+        pub fn flow_on(&self, from: &Nid, position: usize) -> K {
+            self.edge_flows
+                .get(&(from.clone(), position))
+                .copied()
+                .unwrap_or_default()
+        }
+    }
+
+    pub fn min_cost_max_flow<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        source: &Nid,
+        sink: &Nid,
+        capacity_and_cost: F,
+    ) -> Result<MinCostFlow<Nid, K>, &'static str>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure + Sub<Output = K> + Mul<Output = K>,
+        F: FnMut(&E) -> (K, K),
+    {
+        successive_shortest_paths(graph, source, sink, None, capacity_and_cost)
+    }
+
+    pub fn min_cost_flow<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        source: &Nid,
+        sink: &Nid,
+        amount: K,
+        capacity_and_cost: F,
+    ) -> Result<MinCostFlow<Nid, K>, &'static str>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure + Sub<Output = K> + Mul<Output = K>,
+        F: FnMut(&E) -> (K, K),
+    {
+        successive_shortest_paths(graph, source, sink, Some(amount), capacity_and_cost)
+    }
+
+    fn successive_shortest_paths<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        source: &Nid,
+        sink: &Nid,
+        amount: Option<K>,
+        mut capacity_and_cost: F,
+    ) -> Result<MinCostFlow<Nid, K>, &'static str>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure + Sub<Output = K> + Mul<Output = K>,
+        F: FnMut(&E) -> (K, K),
+    {
+        let zero = K::default();
+        // One cost per edge, the reverse arc `2 * edge + 1` costs its negation and
+        // is only ever used in reduced form, which keeps unsigned costs usable.
+        let mut cost: Vec<K> = vec![];
+        let mut network = Residual::from_graph(graph, |edge| {
+            let (capacity, edge_cost) = capacity_and_cost(edge);
+            cost.push(edge_cost);
+            capacity
+        });
+        let count = network.node_ids.len();
+
+        let mut value = zero;
+        if let Some((source, sink)) = endpoints(&network, source, sink) {
+            let mut potential = initial_potentials(&network, &cost)?;
+            while amount.is_none_or(|amount| value < amount) {
+                let mut distance: Vec<Option<K>> = vec![None; count];
+                let mut parent_arc = vec![UNREACHED; count];
+                let mut done = vec![false; count];
+                let mut heap = BinaryHeap::new();
+                distance[source] = Some(zero);
+                heap.push(MinScored(zero, source));
+                while let Some(MinScored(reduced, node)) = heap.pop() {
+                    if done[node] {
+                        continue;
+                    }
+                    done[node] = true;
+                    for &arc in network.arcs_from[node].iter() {
+                        let next = network.head[arc];
+                        if done[next] || network.residual[arc] <= zero {
+                            continue;
+                        }
+                        let next_reduced = if arc % 2 == 0 {
+                            reduced + cost[arc / 2] + potential[node] - potential[next]
+                        } else {
+                            reduced + potential[node] - (potential[next] + cost[arc / 2])
+                        };
+                        if distance[next].is_none_or(|current| next_reduced < current) {
+                            distance[next] = Some(next_reduced);
+                            parent_arc[next] = arc;
+                            heap.push(MinScored(next_reduced, next));
+                        }
+                    }
+                }
+                if distance[sink].is_none() {
+                    break;
+                }
+                for node in 0..count {
+                    if let Some(reduced) = distance[node] {
+                        potential[node] = potential[node] + reduced;
+                    }
+                }
+
+                let mut bottleneck = network.residual[parent_arc[sink]];
+                let mut node = sink;
+                while node != source {
+                    bottleneck = min(bottleneck, network.residual[parent_arc[node]]);
+                    node = network.tail(parent_arc[node]);
+                }
+                if let Some(amount) = amount {
+                    bottleneck = min(bottleneck, amount - value);
+                }
+                let mut node = sink;
+                while node != source {
+                    network.augment(parent_arc[node], bottleneck);
+                    node = network.tail(parent_arc[node]);
+                }
+                value = value + bottleneck;
+            }
+        }
+
+        if amount.is_some_and(|amount| value < amount) {
+            return Err("Requested flow amount exceeds the network capacity.");
+        }
+        let total_cost = (0..network.origin.len()).fold(zero, |total, edge| {
+            total + network.residual[2 * edge + 1] * cost[edge]
+        });
+        Ok(MinCostFlow {
+            value,
+            cost: total_cost,
+            edge_flows: network.edge_flows(),
+        })
+    }
+
+    // Bellman-Ford over the edges with capacity, started from every node at once, so
+    // negative edge costs keep each reduced cost non-negative for the Dijkstra rounds.
+    // Reverse arcs carry no flow yet and are skipped.
+    fn initial_potentials<Nid, K>(
+        network: &Residual<'_, Nid, K>,
+        cost: &[K],
+    ) -> Result<Vec<K>, &'static str>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure + Sub<Output = K>,
+    {
+        let count = network.node_ids.len();
+        let mut distance: Vec<K> = vec![K::default(); count];
+        for round in 0..=count {
+            let mut relaxed = false;
+            for node in 0..count {
+                for &arc in network.arcs_from[node].iter() {
+                    if arc % 2 == 1 || network.residual[arc] <= K::default() {
+                        continue;
+                    }
+                    let next = network.head[arc];
+                    let candidate = distance[node] + cost[arc / 2];
+                    if candidate < distance[next] {
+                        distance[next] = candidate;
+                        relaxed = true;
+                    }
+                }
+            }
+            if !relaxed {
+                break;
+            }
+            if round == count {
+                return Err("Network has a negative cost cycle.");
+            }
+        }
+        Ok(distance)
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct Circulation<Nid, K> {
+        // Keyed by the edge's source node and its position in `Graph::edges_from`.
+        pub edge_flows: HashMap<(Nid, usize), K>,
+    }
+
+    impl<Nid, K> Circulation<Nid, K>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure,
+    {
+        pub fn flow_on(&self, from: &Nid, position: usize) -> K {
+            self.edge_flows
+                .get(&(from.clone(), position))
+                .copied()
+                .unwrap_or_default()
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    pub enum CirculationError<Nid, K> {
+        InvertedBounds {
+            from: Nid,
+            position: usize,
+        },
+        Unbalanced {
+            total_supply: K,
+        },
+        // More supply sits inside `nodes` than the upper bounds of the edges leaving
+        // it, minus the lower bounds of the edges entering it, can carry out.
+        ViolatedCut {
+            nodes: HashSet<Nid>,
+            supply: K,
+            capacity: K,
+        },
+    }
+
+    // Supply is the net amount a node sends out, demands are negative supplies, so
+    // `K` has to be signed.
+    pub fn circulation<Nid, N, E, K, S, F>(
+        graph: &Graph<Nid, N, E>,
+        mut supply: S,
+        mut bounds: F,
+    ) -> Result<Circulation<Nid, K>, CirculationError<Nid, K>>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure + Sub<Output = K> + Neg<Output = K>,
+        S: FnMut(&N) -> K,
+        F: FnMut(&E) -> (K, K),
+    {
+        let zero = K::default();
+        let mut lower_bounds: Vec<K> = vec![];
+        let mut network = Residual::from_graph(graph, |edge| {
+            let (lower, upper) = bounds(edge);
+            lower_bounds.push(lower);
+            upper - lower
+        });
+        for (edge, (from, position)) in network.origin.iter().enumerate() {
+            if network.residual[2 * edge] < zero {
+                return Err(CirculationError::InvertedBounds {
+                    from: (*from).clone(),
+                    position: *position,
+                });
+            }
+        }
+
+        let mut balance = vec![zero; network.node_ids.len()];
+        for (node_id, node) in graph.iter_nodes() {
+            balance[network.index[node_id]] = supply(node);
+        }
+        let total_supply = balance.iter().fold(zero, |total, supply| total + *supply);
+        if total_supply != zero {
+            return Err(CirculationError::Unbalanced { total_supply });
+        }
+
+        let original_supply = balance.clone();
+        for (edge, lower) in lower_bounds.iter().enumerate() {
+            let (from, to) = (network.tail(2 * edge), network.head[2 * edge]);
+            balance[from] = balance[from] - *lower;
+            balance[to] = balance[to] + *lower;
+        }
+        let source = network.add_node();
+        let sink = network.add_node();
+        let mut required = zero;
+        for (node, excess) in balance.iter().enumerate() {
+            if *excess > zero {
+                network.add_arc(source, node, *excess);
+                required = required + *excess;
+            } else if *excess < zero {
+                network.add_arc(node, sink, -*excess);
+            }
+        }
+
+        let value = network.blocking_flows(source, sink);
+        if value < required {
+            let reached = network.reachable_from(source);
+            let mut supply = zero;
+            let mut capacity = zero;
+            for (node, node_supply) in original_supply.iter().enumerate() {
+                if reached[node] {
+                    supply = supply + *node_supply;
+                }
+            }
+            for (edge, lower) in lower_bounds.iter().enumerate() {
+                let (from, to) = (network.tail(2 * edge), network.head[2 * edge]);
+                if reached[from] && !reached[to] {
+                    capacity = capacity
+                        + *lower
+                        + network.residual[2 * edge]
+                        + network.residual[2 * edge + 1];
+                } else if !reached[from] && reached[to] {
+                    capacity = capacity - *lower;
+                }
+            }
+            let nodes = network
+                .node_ids
+                .iter()
+                .zip(reached)
+                .filter(|(_, reached)| *reached)
+                .map(|(node_id, _)| (*node_id).clone())
+                .collect();
+            return Err(CirculationError::ViolatedCut {
+                nodes,
+                supply,
+                capacity,
+            });
+        }
+
+        let edge_flows = network
+            .origin
+            .iter()
+            .enumerate()
+            .map(|(edge, (from, position))| {
+                (
+                    ((*from).clone(), *position),
+                    lower_bounds[edge] + network.residual[2 * edge + 1],
+                )
+            })
+            .collect();
+        Ok(Circulation { edge_flows })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn missing_or_equal_endpoints_give_an_empty_flow() {
+            let empty: Graph<u32, (), u32> = Graph::new();
+            for result in [
+                edmonds_karp(&empty, &0, &1, |capacity| *capacity),
+                dinic(&empty, &0, &1, |capacity| *capacity),
+                push_relabel(&empty, &0, &1, |capacity| *capacity),
+            ] {
+                assert_eq!(result.value, 0);
+                assert!(result.edge_flows.is_empty());
+                assert!(result.source_side.is_empty());
+            }
+
+            let mut graph: Graph<u32, (), u32> = Graph::new();
+            graph.add_edge(5, 6, 3);
+            graph.add_edge(0, 5, 2);
+            let result = dinic(&graph, &0, &9, |capacity| *capacity);
+            assert_eq!(result.value, 0);
+            assert_eq!(result.source_side, HashSet::from([0, 5, 6]));
+            assert_eq!(result.edge_flows.len(), 2);
+            assert!(result.edge_flows.values().all(|flow| *flow == 0));
+            assert!(result.min_cut_edges(&graph).is_empty());
+
+            let result = push_relabel(&graph, &5, &5, |capacity| *capacity);
+            assert_eq!(result.source_side, HashSet::from([5, 6]));
+            assert_eq!(result.flow_on(&5, 0), 0);
+            assert_eq!(result.min_cut_edges(&graph), vec![]);
+
+            let result = edmonds_karp(&graph, &7, &5, |capacity| *capacity);
+            assert!(result.source_side.is_empty());
+            assert_eq!(result.edge_flows.len(), 2);
+        }
+
+        fn textbook() -> Graph<&'static str, (), u32> {
+            let mut graph = Graph::new();
+            for (from, to, capacity) in [
+                ("s", "a", 16),
+                ("s", "b", 13),
+                ("a", "c", 12),
+                ("b", "a", 4),
+                ("b", "d", 14),
+                ("c", "b", 9),
+                ("c", "t", 20),
+                ("d", "c", 7),
+                ("d", "t", 4),
+            ] {
+                graph.add_edge(from, to, capacity);
+            }
+            graph
+        }
+
+        fn assert_conserved(
+            graph: &Graph<&'static str, (), u32>,
+            result: &MaxFlow<&'static str, u32>,
+        ) {
+            let mut balance: HashMap<&str, i64> = HashMap::new();
+            for (from, edges) in graph.iter_edges() {
+                for (position, (to, capacity)) in edges.iter().enumerate() {
+                    let flow = result.flow_on(from, position);
+                    assert!(flow <= *capacity);
+                    *balance.entry(from).or_default() -= flow as i64;
+                    *balance.entry(to).or_default() += flow as i64;
+                }
+            }
+            assert_eq!(balance["s"], -(result.value as i64));
+            assert_eq!(balance["t"], result.value as i64);
+            for node_id in ["a", "b", "c", "d"] {
+                assert_eq!(balance[node_id], 0);
+            }
+        }
+
+        #[test]
+        fn every_max_flow_algorithm_finds_the_textbook_value() {
+            let graph = textbook();
+            for result in [
+                max_flow(&graph, &"s", &"t", |capacity| *capacity),
+                edmonds_karp(&graph, &"s", &"t", |capacity| *capacity),
+                dinic(&graph, &"s", &"t", |capacity| *capacity),
+                push_relabel(&graph, &"s", &"t", |capacity| *capacity),
+            ] {
+                assert_eq!(result.value, 23);
+                assert_conserved(&graph, &result);
+                assert_eq!(result.source_side, HashSet::from(["s", "a", "b", "d"]));
+
+                let cut = result.min_cut_edges(&graph);
+                assert_eq!(cut.len(), 3);
+                let capacity: u32 = cut
+                    .iter()
+                    .map(|(from, position)| graph.edges_from(from).unwrap()[*position].1)
+                    .sum();
+                assert_eq!(capacity, 23);
+            }
+        }
+
+        #[test]
+        fn disconnected_sinks_get_no_flow() {
+            let mut graph = textbook();
+            graph.insert_node("x", ());
+            let result = edmonds_karp(&graph, &"s", &"x", |capacity| *capacity);
+            assert_eq!(result.value, 0);
+            assert!(result.min_cut_edges(&graph).is_empty());
+            assert!(result.source_side.contains("t"));
+            assert!(!result.source_side.contains("x"));
+        }
+
+        // Edges carry (capacity, cost).
+        fn priced(a_to_t: i64) -> Graph<&'static str, (), (i64, i64)> {
+            let mut graph = Graph::new();
+            graph.add_edge("s", "a", (2, 1));
+            graph.add_edge("s", "b", (1, 2));
+            graph.add_edge("a", "b", (1, 1));
+            graph.add_edge("a", "t", (1, a_to_t));
+            graph.add_edge("b", "t", (2, 1));
+            graph
+        }
+
+        #[test]
+        fn min_cost_max_flow_saturates_at_the_lowest_cost() {
+            let graph = priced(3);
+            let result = min_cost_max_flow(&graph, &"s", &"t", |edge| *edge).unwrap();
+            assert_eq!(result.value, 3);
+            assert_eq!(result.cost, 10);
+            assert_eq!(result.flow_on(&"s", 0), 2);
+            assert_eq!(result.flow_on(&"b", 0), 2);
+        }
+
+        #[test]
+        fn min_cost_flow_sends_the_requested_amount() {
+            let graph = priced(3);
+            let result = min_cost_flow(&graph, &"s", &"t", 2, |edge| *edge).unwrap();
+            assert_eq!((result.value, result.cost), (2, 6));
+            assert_eq!(result.flow_on(&"a", 1), 0);
+
+            let result = min_cost_flow(&priced(-2), &"s", &"t", 1, |edge| *edge).unwrap();
+            assert_eq!((result.value, result.cost), (1, -1));
+            assert_eq!(result.flow_on(&"a", 1), 1);
+
+            assert_eq!(
+                min_cost_flow(&graph, &"s", &"t", 4, |edge| *edge).unwrap_err(),
+                "Requested flow amount exceeds the network capacity."
+            );
+            let result = min_cost_flow(&graph, &"s", &"s", 0, |edge| *edge).unwrap();
+            assert_eq!((result.value, result.cost), (0, 0));
+        }
+
+        #[test]
+        fn min_cost_flow_accepts_unsigned_costs() {
+            let mut graph: Graph<&'static str, (), (u32, u32)> = Graph::new();
+            for (from, to, edge) in [
+                ("s", "a", (2, 1)),
+                ("s", "b", (1, 2)),
+                ("a", "b", (1, 1)),
+                ("a", "t", (1, 3)),
+                ("b", "t", (2, 1)),
+            ] {
+                graph.add_edge(from, to, edge);
+            }
+            let result = min_cost_max_flow(&graph, &"s", &"t", |edge| *edge).unwrap();
+            assert_eq!((result.value, result.cost), (3, 10));
+            let result = min_cost_flow(&graph, &"s", &"t", 2, |edge| *edge).unwrap();
+            assert_eq!((result.value, result.cost), (2, 6));
+        }
+
+        #[test]
+        fn min_cost_flow_rejects_negative_cost_cycles() {
+            let mut graph = priced(3);
+            graph.add_edge("t", "s", (1, -10));
+            assert_eq!(
+                min_cost_max_flow(&graph, &"s", &"t", |edge| *edge).unwrap_err(),
+                "Network has a negative cost cycle."
+            );
+        }
+
+        // Nodes carry their supply, edges their (lower, upper) bounds.
+        fn bounded(supply: i64) -> Graph<&'static str, i64, (i64, i64)> {
+            let mut graph = Graph::new();
+            graph.insert_node("a", supply);
+            graph.insert_node("b", 0);
+            graph.insert_node("c", -supply);
+            graph.add_edge("a", "b", (1, 4));
+            graph.add_edge("b", "c", (1, 2));
+            graph.add_edge("a", "c", (0, 1));
+            graph
+        }
+
+        fn assert_feasible(
+            graph: &Graph<&'static str, i64, (i64, i64)>,
+            result: &Circulation<&'static str, i64>,
+        ) {
+            let mut balance: HashMap<&str, i64> = HashMap::new();
+            for (from, edges) in graph.iter_edges() {
+                for (position, (to, (lower, upper))) in edges.iter().enumerate() {
+                    let flow = result.flow_on(from, position);
+                    assert!(*lower <= flow && flow <= *upper);
+                    *balance.entry(from).or_default() += flow;
+                    *balance.entry(to).or_default() -= flow;
+                }
+            }
+            for (node_id, supply) in graph.iter_nodes() {
+                assert_eq!(balance.get(node_id).copied().unwrap_or_default(), *supply);
+            }
+        }
+
+        #[test]
+        fn circulation_meets_supplies_and_bounds() {
+            let graph = bounded(3);
+            let result = circulation(&graph, |supply| *supply, |bounds| *bounds).unwrap();
+            assert_feasible(&graph, &result);
+
+            let mut cycle: Graph<&'static str, i64, (i64, i64)> = Graph::new();
+            cycle.insert_node("a", 0);
+            cycle.insert_node("b", 0);
+            cycle.add_edge("a", "b", (2, 5));
+            cycle.add_edge("b", "a", (1, 3));
+            let result = circulation(&cycle, |supply| *supply, |bounds| *bounds).unwrap();
+            assert_feasible(&cycle, &result);
+        }
+
+        #[test]
+        fn circulation_reports_why_it_is_infeasible() {
+            let mut graph = bounded(3);
+            graph.add_edge("c", "b", (3, 1));
+            assert!(matches!(
+                circulation(&graph, |supply| *supply, |bounds| *bounds),
+                Err(CirculationError::InvertedBounds {
+                    from: "c",
+                    position: 0
+                })
+            ));
+
+            let mut graph = bounded(3);
+            graph.insert_node("b", 1);
+            assert!(matches!(
+                circulation(&graph, |supply| *supply, |bounds| *bounds),
+                Err(CirculationError::Unbalanced { total_supply: 1 })
+            ));
+
+            match circulation(&bounded(6), |supply| *supply, |bounds| *bounds) {
+                Err(CirculationError::ViolatedCut {
+                    nodes,
+                    supply,
+                    capacity,
+                }) => {
+                    assert_eq!(nodes, HashSet::from(["a", "b"]));
+                    assert_eq!((supply, capacity), (6, 3));
+                }
+                other => panic!("expected a violated cut, got {:?}", other),
+            }
+        }
+    }
+}
+
+pub mod matching {
+    use super::graph::Graph;
+    use super::shortest_path::Measure;
+    use super::*;
+    use std::ops::{Div, Neg, Sub};
+
+    const UNMATCHED: usize = usize::MAX;
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct OddCycle<Nid>(pub Vec<Nid>);
+
+    fn two_coloring(neighbours: &[Vec<usize>]) -> Result<Vec<usize>, Vec<usize>> {
+        let count = neighbours.len();
+        let mut color = vec![UNMATCHED; count];
+        let mut parent = vec![UNMATCHED; count];
+        let mut depth = vec![0; count];
+        for root in 0..count {
+            if color[root] != UNMATCHED {
+                continue;
+            }
+            color[root] = 0;
+            let mut queue = VecDeque::from([root]);
+            while let Some(node) = queue.pop_front() {
+                for &next in neighbours[node].iter() {
+                    if color[next] == UNMATCHED {
+                        color[next] = 1 - color[node];
+                        parent[next] = node;
+                        depth[next] = depth[node] + 1;
+                        queue.push_back(next);
+                    } else if color[next] == color[node] {
+                        // Both BFS tree paths meet at a common ancestor, closing an odd cycle.
+                        let (mut a, mut b) = (node, next);
+                        let mut from_a = vec![];
+                        let mut from_b = vec![];
+                        while depth[a] > depth[b] {
+                            from_a.push(a);
+                            a = parent[a];
+                        }
+                        while depth[b] > depth[a] {
+                            from_b.push(b);
+                            b = parent[b];
+                        }
+                        while a != b {
+                            from_a.push(a);
+                            from_b.push(b);
+                            a = parent[a];
+                            b = parent[b];
+                        }
+                        from_a.push(a);
+                        from_a.extend(from_b.into_iter().rev());
+                        return Err(from_a);
+                    }
+                }
+            }
+        }
+        Ok(color)
+    }
+
+    pub fn is_bipartite<Nid, N, E>(
+        graph: &Graph<Nid, N, E>,
+    ) -> Result<HashMap<Nid, usize>, OddCycle<Nid>>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        let (node_ids, index) = graph.index_nodes();
+        let neighbours = graph.neighbour_lists(&index);
+        match two_coloring(&neighbours) {
+            Ok(color) => Ok(node_ids.into_iter().cloned().zip(color).collect()),
+            Err(cycle) => Err(OddCycle(
+                cycle
+                    .into_iter()
+                    .map(|node| node_ids[node].clone())
+                    .collect(),
+            )),
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct BipartiteMatching<Nid> {
+        pub pairs: Vec<(Nid, Nid)>,
+        pub vertex_cover: Vec<Nid>,
+        mates: HashMap<Nid, Nid>,
+        node_count: usize,
+    }
+
+    impl<Nid> BipartiteMatching<Nid>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        pub fn mate(&self, node_id: &Nid) -> Option<&Nid> {
+            self.mates.get(node_id)
+        }
+
+        pub fn len(&self) -> usize {
+            self.pairs.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.pairs.is_empty()
+        }
+
+        pub fn is_perfect(&self) -> bool {
+            self.mates.len() == self.node_count
+        }
+    }
+
+    pub fn maximum_bipartite_matching<Nid, N, E>(
+        graph: &Graph<Nid, N, E>,
+    ) -> Result<BipartiteMatching<Nid>, OddCycle<Nid>>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        let (node_ids, index) = graph.index_nodes();
+        let neighbours = graph.neighbour_lists(&index);
+        let color = match two_coloring(&neighbours) {
+            Ok(color) => color,
+            Err(cycle) => {
+                return Err(OddCycle(
+                    cycle
+                        .into_iter()
+                        .map(|node| node_ids[node].clone())
+                        .collect(),
+                ))
+            }
+        };
+        let count = node_ids.len();
+        let left: Vec<usize> = (0..count).filter(|node| color[*node] == 0).collect();
+        let mate = hopcroft_karp(&neighbours, &left);
+
+        // Koenig: whatever alternating paths reach from the free left nodes, take the
+        // right nodes among them and the left nodes outside of them.
+        let mut reached = vec![false; count];
+        let mut queue: VecDeque<usize> = left
+            .iter()
+            .copied()
+            .filter(|node| mate[*node] == UNMATCHED)
+            .collect();
+        for node in queue.iter() {
+            reached[*node] = true;
+        }
+        while let Some(node) = queue.pop_front() {
+            for &next in neighbours[node].iter() {
+                if reached[next] || mate[node] == next {
+                    continue;
+                }
+                reached[next] = true;
+                let back = mate[next];
+                if back != UNMATCHED && !reached[back] {
+                    reached[back] = true;
+                    queue.push_back(back);
+                }
+            }
+        }
+        let vertex_cover = (0..count)
+            .filter(|node| (color[*node] == 0) != reached[*node])
+            .map(|node| node_ids[node].clone())
+            .collect();
+
+        let pairs = left
+            .iter()
+            .filter(|node| mate[**node] != UNMATCHED)
+            .map(|node| (node_ids[*node].clone(), node_ids[mate[*node]].clone()))
+            .collect();
+        let mates = (0..count)
+            .filter(|node| mate[*node] != UNMATCHED)
+            .map(|node| (node_ids[node].clone(), node_ids[mate[node]].clone()))
+            .collect();
+        Ok(BipartiteMatching {
+            pairs,
+            vertex_cover,
+            mates,
+            node_count: count,
+        })
+    }
+
+    fn hopcroft_karp(neighbours: &[Vec<usize>], left: &[usize]) -> Vec<usize> {
+        let count = neighbours.len();
+        let mut mate = vec![UNMATCHED; count];
+        let mut layer = vec![UNMATCHED; count];
+        loop {
+            let mut queue = VecDeque::new();
+            for &node in left.iter() {
+                if mate[node] == UNMATCHED {
+                    layer[node] = 0;
+                    queue.push_back(node);
+                } else {
+                    layer[node] = UNMATCHED;
+                }
+            }
+            let mut found = false;
+            while let Some(node) = queue.pop_front() {
+                for &next in neighbours[node].iter() {
+                    match mate[next] {
+                        UNMATCHED => found = true,
+                        back if layer[back] == UNMATCHED => {
+                            layer[back] = layer[node] + 1;
+                            queue.push_back(back);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            if !found {
+                return mate;
+            }
+
+            let mut current = vec![0; count];
+            for &root in left.iter() {
+                if mate[root] != UNMATCHED {
+                    continue;
+                }
+                let mut stack = vec![root];
+                let mut via: Vec<usize> = vec![];
+                while let Some(&node) = stack.last() {
+                    match neighbours[node].get(current[node]) {
+                        Some(&next) => {
+                            current[node] += 1;
+                            let back = mate[next];
+                            if back == UNMATCHED {
+                                via.push(next);
+                                for (&left_node, &right_node) in stack.iter().zip(via.iter()) {
+                                    mate[left_node] = right_node;
+                                    mate[right_node] = left_node;
+                                }
+                                break;
+                            }
+                            if layer[back] == layer[node] + 1 {
+                                via.push(next);
+                                stack.push(back);
+                            }
+                        }
+                        None => {
+                            layer[node] = UNMATCHED;
+                            stack.pop();
+                            via.pop();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+    pub enum Slot {
+        Row(usize),
+        Column(usize),
+    }
+
+    pub fn from_cost_matrix<K>(costs: &[Vec<K>]) -> Graph<Slot, (), K>
+    where
+        K: Clone,
+    {
+        let mut graph = Graph::new();
+        for (row, costs) in costs.iter().enumerate() {
+            graph.insert_node(Slot::Row(row), ());
+            for (column, cost) in costs.iter().enumerate() {
+                graph.insert_node(Slot::Column(column), ());
+                graph.add_edge(Slot::Row(row), Slot::Column(column), cost.clone());
+            }
+        }
+        graph
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct Assignment<Nid, K> {
+        pub pairs: Vec<(Nid, Nid)>,
+        pub total: K,
+    }
+
+    pub fn min_cost_assignment<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        cost: F,
+    ) -> Result<Assignment<Nid, K>, &'static str>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure + Sub<Output = K> + Neg<Output = K>,
+        F: FnMut(&E) -> K,
+    {
+        kuhn_munkres(graph, cost, false)
+    }
+
+    pub fn max_weight_assignment<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        weight: F,
+    ) -> Result<Assignment<Nid, K>, &'static str>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure + Sub<Output = K> + Neg<Output = K>,
+        F: FnMut(&E) -> K,
+    {
+        kuhn_munkres(graph, weight, true)
+    }
+
+    // Flips the colours of every component whose colour 0 side is the larger one,
+    // so the rows never outnumber the columns within a component.
+    fn smaller_side_first(neighbours: &[Vec<usize>], color: &mut [usize]) {
+        let mut seen = vec![false; neighbours.len()];
+        for root in 0..neighbours.len() {
+            if seen[root] {
+                continue;
+            }
+            seen[root] = true;
+            let mut component = vec![root];
+            let mut position = 0;
+            while let Some(&node) = component.get(position) {
+                position += 1;
+                for &next in neighbours[node].iter() {
+                    if !seen[next] {
+                        seen[next] = true;
+                        component.push(next);
+                    }
+                }
+            }
+            if 2 * component.iter().filter(|node| color[**node] == 0).count() > component.len() {
+                for node in component {
+                    color[node] = 1 - color[node];
+                }
+            }
+        }
+    }
+
+    // Every node on the smaller side of each connected component gets assigned,
+    // which is a perfect matching whenever both sides have the same size.
+    // Potentials go negative along the way, so `K` has to be signed.
+    fn kuhn_munkres<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        mut cost: F,
+        maximize: bool,
+    ) -> Result<Assignment<Nid, K>, &'static str>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure + Sub<Output = K> + Neg<Output = K>,
+        F: FnMut(&E) -> K,
+    {
+        let zero = K::default();
+        let (node_ids, index) = graph.index_nodes();
+        let neighbours = graph.neighbour_lists(&index);
+        let mut color = two_coloring(&neighbours).map_err(|_| "Graph is not bipartite.")?;
+        smaller_side_first(&neighbours, &mut color);
+        let rows: Vec<usize> = (0..node_ids.len())
+            .filter(|node| color[*node] == 0)
+            .collect();
+        let columns: Vec<usize> = (0..node_ids.len())
+            .filter(|node| color[*node] == 1)
+            .collect();
+        let mut slot = vec![0; node_ids.len()];
+        let mut is_row = vec![false; node_ids.len()];
+        for (position, row) in rows.iter().enumerate() {
+            slot[*row] = position + 1;
+            is_row[*row] = true;
+        }
+        for (position, column) in columns.iter().enumerate() {
+            slot[*column] = position + 1;
+        }
+
+        // One-based with a zero sentinel row and column, as in the classic formulation.
+        let (n, m) = (rows.len(), columns.len());
+        let mut matrix: Vec<Vec<Option<K>>> = vec![vec![None; m + 1]; n + 1];
+        for (from, edges) in graph.iter_edges() {
+            for (to, edge) in edges.iter() {
+                let (from, to) = (index[from], index[to]);
+                let (row, column) = if is_row[from] { (from, to) } else { (to, from) };
+                let value = if maximize { -cost(edge) } else { cost(edge) };
+                let entry = &mut matrix[slot[row]][slot[column]];
+                if entry.is_none_or(|current| value < current) {
+                    *entry = Some(value);
+                }
+            }
+        }
+
+        let mut row_potential = vec![zero; n + 1];
+        let mut column_potential = vec![zero; m + 1];
+        let mut assigned_row = vec![0; m + 1];
+        let mut way = vec![0; m + 1];
+        for row in 1..=n {
+            assigned_row[0] = row;
+            let mut column = 0;
+            let mut slack: Vec<Option<K>> = vec![None; m + 1];
+            let mut used = vec![false; m + 1];
+            loop {
+                used[column] = true;
+                let current_row = assigned_row[column];
+                let mut delta: Option<K> = None;
+                let mut next_column = 0;
+                for candidate in 1..=m {
+                    if used[candidate] {
+                        continue;
+                    }
+                    if let Some(value) = matrix[current_row][candidate] {
+                        let reduced =
+                            value - row_potential[current_row] - column_potential[candidate];
+                        if slack[candidate].is_none_or(|current| reduced < current) {
+                            slack[candidate] = Some(reduced);
+                            way[candidate] = column;
+                        }
+                    }
+                    if let Some(value) = slack[candidate] {
+                        if delta.is_none_or(|current| value < current) {
+                            delta = Some(value);
+                            next_column = candidate;
+                        }
+                    }
+                }
+                let delta = delta.ok_or("No assignment covers every node of the smaller side.")?;
+                for candidate in 0..=m {
+                    if used[candidate] {
+                        let matched = assigned_row[candidate];
+                        row_potential[matched] = row_potential[matched] + delta;
+                        column_potential[candidate] = column_potential[candidate] - delta;
+                    } else if let Some(value) = slack[candidate] {
+                        slack[candidate] = Some(value - delta);
+                    }
+                }
+                column = next_column;
+                if assigned_row[column] == 0 {
+                    break;
+                }
+            }
+            while column != 0 {
+                let previous = way[column];
+                assigned_row[column] = assigned_row[previous];
+                column = previous;
+            }
+        }
+
+        let mut pairs = vec![];
+        let mut total = zero;
+        for column in 1..=m {
+            let row = assigned_row[column];
+            if row == 0 {
+                continue;
+            }
+            let value = matrix[row][column].unwrap();
+            total = if maximize {
+                total - value
+            } else {
+                total + value
+            };
+            let (row, column) = (rows[row - 1], columns[column - 1]);
+            let (row_id, column_id) = (node_ids[row], node_ids[column]);
+            if graph.edge_count(row_id, column_id) > 0 {
+                pairs.push((row_id.clone(), column_id.clone()));
+            } else {
+                pairs.push((column_id.clone(), row_id.clone()));
+            }
+        }
+        Ok(Assignment { pairs, total })
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct Matching<Nid> {
+        pub pairs: Vec<(Nid, Nid)>,
+        mates: HashMap<Nid, Nid>,
+        node_count: usize,
+    }
+
+    impl<Nid> Matching<Nid>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        fn from_mates(node_ids: &[&Nid], mate: &[usize]) -> Self {
+            let mut pairs = vec![];
+            let mut mates = HashMap::new();
+            for (node, &other) in mate.iter().enumerate() {
+                if other == UNMATCHED {
+                    continue;
+                }
+                if node < other {
+                    pairs.push((node_ids[node].clone(), node_ids[other].clone()));
+                }
+                mates.insert(node_ids[node].clone(), node_ids[other].clone());
+            }
+            Matching {
+                pairs,
+                mates,
+                node_count: node_ids.len(),
+            }
+        }
+
+        pub fn mate(&self, node_id: &Nid) -> Option<&Nid> {
+            self.mates.get(node_id)
+        }
+
+        pub fn len(&self) -> usize {
+            self.pairs.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.pairs.is_empty()
+        }
+
+        pub fn is_perfect(&self) -> bool {
+            self.mates.len() == self.node_count
+        }
+    }
+
+    pub fn maximum_matching<Nid, N, E>(graph: &Graph<Nid, N, E>) -> Matching<Nid>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        let (node_ids, index) = graph.index_nodes();
+        let neighbours = graph.neighbour_lists(&index);
+        let count = node_ids.len();
+        let mut mate = vec![UNMATCHED; count];
+
+        for root in 0..count {
+            if mate[root] != UNMATCHED {
+                continue;
+            }
+            let mut search = BlossomSearch::new(count);
+            let mut end = search.augmenting_path(&neighbours, &mate, root);
+            while end != UNMATCHED {
+                let previous = search.parent[end];
+                let next = mate[previous];
+                mate[end] = previous;
+                mate[previous] = end;
+                end = next;
+            }
+        }
+
+        Matching::from_mates(&node_ids, &mate)
+    }
+
+    // One alternating-tree search of Edmonds' algorithm, odd cycles get shrunk by
+    // pointing every vertex of the blossom at a shared base.
+    struct BlossomSearch {
+        parent: Vec<usize>,
+        base: Vec<usize>,
+        used: Vec<bool>,
+    }
+
+    impl BlossomSearch {
+        fn new(count: usize) -> Self {
+            BlossomSearch {
+                parent: vec![UNMATCHED; count],
+                base: (0..count).collect(),
+                used: vec![false; count],
+            }
+        }
+
+        fn common_base(&self, mate: &[usize], mut a: usize, mut b: usize) -> usize {
+            let mut on_path = vec![false; mate.len()];
+            loop {
+                a = self.base[a];
+                on_path[a] = true;
+                if mate[a] == UNMATCHED {
+                    break;
+                }
+                a = self.parent[mate[a]];
+            }
+            loop {
+                b = self.base[b];
+                if on_path[b] {
+                    return b;
+                }
+                b = self.parent[mate[b]];
+            }
+        }
+
+        fn mark_path(
+            &mut self,
+            mate: &[usize],
+            in_blossom: &mut [bool],
+            mut node: usize,
+            base: usize,
+            mut child: usize,
+        ) {
+            while self.base[node] != base {
+                in_blossom[self.base[node]] = true;
+                in_blossom[self.base[mate[node]]] = true;
+                self.parent[node] = child;
+                child = mate[node];
+                node = self.parent[mate[node]];
+            }
+        }
+
+        fn augmenting_path(
+            &mut self,
+            neighbours: &[Vec<usize>],
+            mate: &[usize],
+            root: usize,
+        ) -> usize {
+            let count = neighbours.len();
+            self.used[root] = true;
+            let mut queue = VecDeque::from([root]);
+            while let Some(node) = queue.pop_front() {
+                for &next in neighbours[node].iter() {
+                    if self.base[node] == self.base[next] || mate[node] == next {
+                        continue;
+                    }
+                    if next == root
+                        || (mate[next] != UNMATCHED && self.parent[mate[next]] != UNMATCHED)
+                    {
+                        let base = self.common_base(mate, node, next);
+                        let mut in_blossom = vec![false; count];
+                        self.mark_path(mate, &mut in_blossom, node, base, next);
+                        self.mark_path(mate, &mut in_blossom, next, base, node);
+                        for vertex in 0..count {
+                            if in_blossom[self.base[vertex]] {
+                                self.base[vertex] = base;
+                                if !self.used[vertex] {
+                                    self.used[vertex] = true;
+                                    queue.push_back(vertex);
+                                }
+                            }
+                        }
+                    } else if self.parent[next] == UNMATCHED {
+                        self.parent[next] = node;
+                        if mate[next] == UNMATCHED {
+                            return next;
+                        }
+                        self.used[mate[next]] = true;
+                        queue.push_back(mate[next]);
+                    }
+                }
+            }
+            UNMATCHED
+        }
+    }
+
+    pub fn max_weight_matching<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        mut weight: F,
+        max_cardinality: bool,
+    ) -> (Matching<Nid>, K)
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure + Sub<Output = K> + Div<Output = K> + From<u8>,
+        F: FnMut(&E) -> K,
+    {
+        let (node_ids, index) = graph.index_nodes();
+        let mut heaviest: HashMap<(usize, usize), K> = HashMap::new();
+        for (from, edges) in graph.iter_edges() {
+            for (to, edge) in edges.iter() {
+                let (a, b) = (index[from], index[to]);
+                if a == b {
+                    continue;
+                }
+                let value = weight(edge);
+                let entry = heaviest.entry((a.min(b), a.max(b))).or_insert(value);
+                if *entry < value {
+                    *entry = value;
+                }
+            }
+        }
+        let edges: Vec<(usize, usize, K)> = heaviest
+            .iter()
+            .map(|((a, b), value)| (*a, *b, *value))
+            .collect();
+
+        let mate = WeightedBlossom::new(node_ids.len(), edges, max_cardinality).solve();
+        let mut total = K::default();
+        for (node, &other) in mate.iter().enumerate() {
+            if other != UNMATCHED && node < other {
+                total = total + heaviest[&(node, other)];
+            }
+        }
+        (Matching::from_mates(&node_ids, &mate), total)
+    }
+
+    // Primal-dual weighted blossom algorithm after Galil's exposition, as popularised
+    // by van Rantwijk. Vertex duals are stored doubled so integer weights stay exact.
+    // Blossoms are numbered after the vertices, edge `k` has endpoints `2k` and `2k + 1`.
+    struct WeightedBlossom<K> {
+        vertex_count: usize,
+        edges: Vec<(usize, usize, K)>,
+        max_cardinality: bool,
+        endpoint: Vec<usize>,
+        neighbour_ends: Vec<Vec<usize>>,
+        mate: Vec<usize>,
+        label: Vec<u8>,
+        label_end: Vec<usize>,
+        in_blossom: Vec<usize>,
+        blossom_parent: Vec<usize>,
+        blossom_children: Vec<Vec<usize>>,
+        blossom_base: Vec<usize>,
+        blossom_ends: Vec<Vec<usize>>,
+        best_edge: Vec<usize>,
+        blossom_best_edges: Vec<Option<Vec<usize>>>,
+        unused_blossoms: Vec<usize>,
+        dual: Vec<K>,
+        allowed: Vec<bool>,
+        queue: Vec<usize>,
+    }
+
+    impl<K> WeightedBlossom<K>
+    where
+        K: Measure + Sub<Output = K> + Div<Output = K> + From<u8>,
+    {
+        fn new(vertex_count: usize, edges: Vec<(usize, usize, K)>, max_cardinality: bool) -> Self {
+            let zero = K::default();
+            let max_weight = edges.iter().fold(
+                zero,
+                |max, (_, _, value)| if max < *value { *value } else { max },
+            );
+            let mut endpoint = Vec::with_capacity(2 * edges.len());
+            let mut neighbour_ends = vec![vec![]; vertex_count];
+            for (k, (i, j, _)) in edges.iter().enumerate() {
+                endpoint.push(*i);
+                endpoint.push(*j);
+                neighbour_ends[*i].push(2 * k + 1);
+                neighbour_ends[*j].push(2 * k);
+            }
+            let blossoms = 2 * vertex_count;
+            WeightedBlossom {
+                vertex_count,
+                max_cardinality,
+                endpoint,
+                neighbour_ends,
+                mate: vec![UNMATCHED; vertex_count],
+                label: vec![0; blossoms],
+                label_end: vec![UNMATCHED; blossoms],
+                in_blossom: (0..vertex_count).collect(),
+                blossom_parent: vec![UNMATCHED; blossoms],
+                blossom_children: vec![vec![]; blossoms],
+                blossom_base: (0..vertex_count)
+                    .chain(std::iter::repeat_n(UNMATCHED, vertex_count))
+                    .collect(),
+                blossom_ends: vec![vec![]; blossoms],
+                best_edge: vec![UNMATCHED; blossoms],
+                blossom_best_edges: vec![None; blossoms],
+                unused_blossoms: (vertex_count..blossoms).collect(),
+                dual: std::iter::repeat_n(max_weight, vertex_count)
+                    .chain(std::iter::repeat_n(zero, vertex_count))
+                    .collect(),
+                allowed: vec![false; edges.len()],
+                queue: vec![],
+                edges,
+            }
+        }
+
+        fn slack(&self, k: usize) -> K {
+            let (i, j, value) = self.edges[k];
+            self.dual[i] + self.dual[j] - value - value
+        }
+
+        fn leaves(&self, blossom: usize) -> Vec<usize> {
+            let mut leaves = vec![];
+            let mut stack = vec![blossom];
+            while let Some(current) = stack.pop() {
+                if current < self.vertex_count {
+                    leaves.push(current);
+                } else {
+                    stack.extend(self.blossom_children[current].iter().rev());
+                }
+            }
+            leaves
+        }
+
+        fn assign_label(&mut self, w: usize, label: u8, p: usize) {
+            let b = self.in_blossom[w];
+            self.label[w] = label;
+            self.label[b] = label;
+            self.label_end[w] = p;
+            self.label_end[b] = p;
+            self.best_edge[w] = UNMATCHED;
+            self.best_edge[b] = UNMATCHED;
+            if label == 1 {
+                let leaves = self.leaves(b);
+                self.queue.extend(leaves);
+            } else if label == 2 {
+                let base_mate = self.mate[self.blossom_base[b]];
+                self.assign_label(self.endpoint[base_mate], 1, base_mate ^ 1);
+            }
+        }
+
+        fn scan_blossom(&mut self, mut v: usize, mut w: usize) -> usize {
+            let mut path = vec![];
+            let mut base = UNMATCHED;
+            while v != UNMATCHED {
+                let b = self.in_blossom[v];
+                if self.label[b] & 4 != 0 {
+                    base = self.blossom_base[b];
+                    break;
+                }
+                path.push(b);
+                self.label[b] = 5;
+                if self.label_end[b] == UNMATCHED {
+                    v = UNMATCHED;
+                } else {
+                    v = self.endpoint[self.label_end[b]];
+                    let b = self.in_blossom[v];
+                    v = self.endpoint[self.label_end[b]];
+                }
+                if w != UNMATCHED {
+                    std::mem::swap(&mut v, &mut w);
+                }
+            }
+            for b in path {
+                self.label[b] = 1;
+            }
+            base
+        }
+
+        fn add_blossom(&mut self, base: usize, k: usize) {
+            let (mut v, mut w, _) = self.edges[k];
+            let bb = self.in_blossom[base];
+            let mut bv = self.in_blossom[v];
+            let mut bw = self.in_blossom[w];
+            let b = self.unused_blossoms.pop().unwrap();
+            self.blossom_base[b] = base;
+            self.blossom_parent[b] = UNMATCHED;
+            self.blossom_parent[bb] = b;
+
+            let mut path = vec![];
+            let mut ends = vec![];
+            while bv != bb {
+                self.blossom_parent[bv] = b;
+                path.push(bv);
+                ends.push(self.label_end[bv]);
+                v = self.endpoint[self.label_end[bv]];
+                bv = self.in_blossom[v];
+            }
+            path.push(bb);
+            path.reverse();
+            ends.reverse();
+            ends.push(2 * k);
+            while bw != bb {
+                self.blossom_parent[bw] = b;
+                path.push(bw);
+                ends.push(self.label_end[bw] ^ 1);
+                w = self.endpoint[self.label_end[bw]];
+                bw = self.in_blossom[w];
+            }
+            self.blossom_children[b] = path.clone();
+            self.blossom_ends[b] = ends;
+
+            self.label[b] = 1;
+            self.label_end[b] = self.label_end[bb];
+            self.dual[b] = K::default();
+            for leaf in self.leaves(b) {
+                if self.label[self.in_blossom[leaf]] == 2 {
+                    self.queue.push(leaf);
+                }
+                self.in_blossom[leaf] = b;
+            }
+
+            let mut best_edge_to = vec![UNMATCHED; 2 * self.vertex_count];
+            for child in path {
+                let lists: Vec<Vec<usize>> = match self.blossom_best_edges[child].take() {
+                    Some(list) => vec![list],
+                    None => self
+                        .leaves(child)
+                        .into_iter()
+                        .map(|leaf| self.neighbour_ends[leaf].iter().map(|p| p / 2).collect())
+                        .collect(),
+                };
+                for k in lists.into_iter().flatten() {
+                    let (i, j, _) = self.edges[k];
+                    let j = if self.in_blossom[j] == b { i } else { j };
+                    let bj = self.in_blossom[j];
+                    if bj != b
+                        && self.label[bj] == 1
+                        && (best_edge_to[bj] == UNMATCHED
+                            || self.slack(k) < self.slack(best_edge_to[bj]))
+                    {
+                        best_edge_to[bj] = k;
+                    }
+                }
+                self.best_edge[child] = UNMATCHED;
+            }
+            let best_edges: Vec<usize> = best_edge_to
+                .into_iter()
+                .filter(|k| *k != UNMATCHED)
+                .collect();
+            self.best_edge[b] = UNMATCHED;
+            for &k in best_edges.iter() {
+                if self.best_edge[b] == UNMATCHED || self.slack(k) < self.slack(self.best_edge[b]) {
+                    self.best_edge[b] = k;
+                }
+            }
+            self.blossom_best_edges[b] = Some(best_edges);
+        }
+
+        // Children are walked around the cycle in whichever direction reaches the
+        // base over an even number of steps.
+        fn walk_direction(&self, b: usize, start: usize) -> (isize, isize, usize) {
+            let length = self.blossom_children[b].len() as isize;
+            let j = start as isize;
+            if j & 1 == 1 {
+                (j - length, 1, 0)
+            } else {
+                (j, -1, 1)
+            }
+        }
+
+        fn wrap(&self, b: usize, j: isize) -> usize {
+            let length = self.blossom_children[b].len() as isize;
+            (((j % length) + length) % length) as usize
+        }
+
+        fn expand_blossom(&mut self, b: usize, end_stage: bool) {
+            let children = self.blossom_children[b].clone();
+            for &child in children.iter() {
+                self.blossom_parent[child] = UNMATCHED;
+                if child < self.vertex_count {
+                    self.in_blossom[child] = child;
+                } else if end_stage && self.dual[child] == K::default() {
+                    self.expand_blossom(child, end_stage);
+                } else {
+                    for leaf in self.leaves(child) {
+                        self.in_blossom[leaf] = child;
+                    }
+                }
+            }
+
+            if !end_stage && self.label[b] == 2 {
+                let entry_child = self.in_blossom[self.endpoint[self.label_end[b] ^ 1]];
+                let start = children.iter().position(|c| *c == entry_child).unwrap();
+                let (mut j, step, trick) = self.walk_direction(b, start);
+                let mut p = self.label_end[b];
+                while j != 0 {
+                    self.label[self.endpoint[p ^ 1]] = 0;
+                    let end = self.blossom_ends[b][self.wrap(b, j - trick as isize)];
+                    self.label[self.endpoint[end ^ trick ^ 1]] = 0;
+                    self.assign_label(self.endpoint[p ^ 1], 2, p);
+                    self.allowed[end / 2] = true;
+                    j += step;
+                    p = self.blossom_ends[b][self.wrap(b, j - trick as isize)] ^ trick;
+                    self.allowed[p / 2] = true;
+                    j += step;
+                }
+                let bv = children[self.wrap(b, j)];
+                self.label[self.endpoint[p ^ 1]] = 2;
+                self.label[bv] = 2;
+                self.label_end[self.endpoint[p ^ 1]] = p;
+                self.label_end[bv] = p;
+                self.best_edge[bv] = UNMATCHED;
+                j += step;
+                while children[self.wrap(b, j)] != entry_child {
+                    let bv = children[self.wrap(b, j)];
+                    if self.label[bv] == 1 {
+                        j += step;
+                        continue;
+                    }
+                    let labelled = self
+                        .leaves(bv)
+                        .into_iter()
+                        .find(|leaf| self.label[*leaf] != 0);
+                    if let Some(leaf) = labelled {
+                        self.label[leaf] = 0;
+                        let base_mate = self.mate[self.blossom_base[bv]];
+                        self.label[self.endpoint[base_mate]] = 0;
+                        self.assign_label(leaf, 2, self.label_end[leaf]);
+                    }
+                    j += step;
+                }
+            }
+
+            self.label[b] = 0;
+            self.label_end[b] = UNMATCHED;
+            self.blossom_children[b] = vec![];
+            self.blossom_ends[b] = vec![];
+            self.blossom_base[b] = UNMATCHED;
+            self.blossom_best_edges[b] = None;
+            self.best_edge[b] = UNMATCHED;
+            self.unused_blossoms.push(b);
+        }
+
+        fn augment_blossom(&mut self, b: usize, v: usize) {
+            let mut t = v;
+            while self.blossom_parent[t] != b {
+                t = self.blossom_parent[t];
+            }
+            if t >= self.vertex_count {
+                self.augment_blossom(t, v);
+            }
+            let start = self.blossom_children[b]
+                .iter()
+                .position(|c| *c == t)
+                .unwrap();
+            let (mut j, step, trick) = self.walk_direction(b, start);
+            while j != 0 {
+                j += step;
+                let t = self.blossom_children[b][self.wrap(b, j)];
+                let p = self.blossom_ends[b][self.wrap(b, j - trick as isize)] ^ trick;
+                if t >= self.vertex_count {
+                    self.augment_blossom(t, self.endpoint[p]);
+                }
+                j += step;
+                let t = self.blossom_children[b][self.wrap(b, j)];
+                if t >= self.vertex_count {
+                    self.augment_blossom(t, self.endpoint[p ^ 1]);
+                }
+                self.mate[self.endpoint[p]] = p ^ 1;
+                self.mate[self.endpoint[p ^ 1]] = p;
+            }
+            self.blossom_children[b].rotate_left(start);
+            self.blossom_ends[b].rotate_left(start);
+            self.blossom_base[b] = self.blossom_base[self.blossom_children[b][0]];
+        }
+
+        fn augment_matching(&mut self, k: usize) {
+            let (v, w, _) = self.edges[k];
+            for (mut s, mut p) in [(v, 2 * k + 1), (w, 2 * k)] {
+                loop {
+                    let bs = self.in_blossom[s];
+                    if bs >= self.vertex_count {
+                        self.augment_blossom(bs, s);
+                    }
+                    self.mate[s] = p;
+                    if self.label_end[bs] == UNMATCHED {
+                        break;
+                    }
+                    let t = self.endpoint[self.label_end[bs]];
+                    let bt = self.in_blossom[t];
+                    s = self.endpoint[self.label_end[bt]];
+                    let j = self.endpoint[self.label_end[bt] ^ 1];
+                    if bt >= self.vertex_count {
+                        self.augment_blossom(bt, j);
+                    }
+                    self.mate[j] = self.label_end[bt];
+                    p = self.label_end[bt] ^ 1;
+                }
+            }
+        }
+
+        fn solve(mut self) -> Vec<usize> {
+            let zero = K::default();
+            let two = K::from(2);
+            let n = self.vertex_count;
+            for _ in 0..n {
+                self.label.fill(0);
+                self.best_edge.fill(UNMATCHED);
+                for best_edges in self.blossom_best_edges[n..].iter_mut() {
+                    *best_edges = None;
+                }
+                self.allowed.fill(false);
+                self.queue.clear();
+                for v in 0..n {
+                    if self.mate[v] == UNMATCHED && self.label[self.in_blossom[v]] == 0 {
+                        self.assign_label(v, 1, UNMATCHED);
+                    }
+                }
+
+                let mut augmented = false;
+                loop {
+                    while let Some(v) = self.queue.pop() {
+                        for position in 0..self.neighbour_ends[v].len() {
+                            let p = self.neighbour_ends[v][position];
+                            let k = p / 2;
+                            let w = self.endpoint[p];
+                            if self.in_blossom[v] == self.in_blossom[w] {
+                                continue;
+                            }
+                            let mut k_slack = zero;
+                            if !self.allowed[k] {
+                                k_slack = self.slack(k);
+                                if k_slack <= zero {
+                                    self.allowed[k] = true;
+                                }
+                            }
+                            if self.allowed[k] {
+                                if self.label[self.in_blossom[w]] == 0 {
+                                    self.assign_label(w, 2, p ^ 1);
+                                } else if self.label[self.in_blossom[w]] == 1 {
+                                    let base = self.scan_blossom(v, w);
+                                    if base != UNMATCHED {
+                                        self.add_blossom(base, k);
+                                    } else {
+                                        self.augment_matching(k);
+                                        augmented = true;
+                                        break;
+                                    }
+                                } else if self.label[w] == 0 {
+                                    self.label[w] = 2;
+                                    self.label_end[w] = p ^ 1;
+                                }
+                            } else if self.label[self.in_blossom[w]] == 1 {
+                                let b = self.in_blossom[v];
+                                if self.best_edge[b] == UNMATCHED
+                                    || k_slack < self.slack(self.best_edge[b])
+                                {
+                                    self.best_edge[b] = k;
+                                }
+                            } else if self.label[w] == 0
+                                && (self.best_edge[w] == UNMATCHED
+                                    || k_slack < self.slack(self.best_edge[w]))
+                            {
+                                self.best_edge[w] = k;
+                            }
+                        }
+                        if augmented {
+                            break;
+                        }
+                    }
+                    if augmented {
+                        break;
+                    }
+
+                    // Pick the smallest dual adjustment that either ends the stage or
+                    // makes a new edge tight.
+                    let mut delta: Option<(K, u8, usize)> = None;
+                    if !self.max_cardinality {
+                        let smallest = self.dual[..n].iter().fold(self.dual[0], |min, d| {
+                            if *d < min {
+                                *d
+                            } else {
+                                min
+                            }
+                        });
+                        delta = Some((smallest, 1, UNMATCHED));
+                    }
+                    for v in 0..n {
+                        if self.label[self.in_blossom[v]] == 0 && self.best_edge[v] != UNMATCHED {
+                            let d = self.slack(self.best_edge[v]);
+                            if delta.is_none_or(|(current, _, _)| d < current) {
+                                delta = Some((d, 2, self.best_edge[v]));
+                            }
+                        }
+                    }
+                    for b in 0..2 * n {
+                        if self.blossom_parent[b] == UNMATCHED
+                            && self.label[b] == 1
+                            && self.best_edge[b] != UNMATCHED
+                        {
+                            let d = self.slack(self.best_edge[b]) / two;
+                            if delta.is_none_or(|(current, _, _)| d < current) {
+                                delta = Some((d, 3, self.best_edge[b]));
+                            }
+                        }
+                    }
+                    for b in n..2 * n {
+                        if self.blossom_base[b] != UNMATCHED
+                            && self.blossom_parent[b] == UNMATCHED
+                            && self.label[b] == 2
+                            && delta.is_none_or(|(current, _, _)| self.dual[b] < current)
+                        {
+                            delta = Some((self.dual[b], 4, b));
+                        }
+                    }
+                    let (delta, kind, target) = delta.unwrap_or_else(|| {
+                        let smallest = self.dual[..n].iter().fold(self.dual[0], |min, d| {
+                            if *d < min {
+                                *d
+                            } else {
+                                min
+                            }
+                        });
+                        (if smallest < zero { zero } else { smallest }, 1, UNMATCHED)
+                    });
+
+                    for v in 0..n {
+                        match self.label[self.in_blossom[v]] {
+                            1 => self.dual[v] = self.dual[v] - delta,
+                            2 => self.dual[v] = self.dual[v] + delta,
+                            _ => {}
+                        }
+                    }
+                    for b in n..2 * n {
+                        if self.blossom_base[b] != UNMATCHED && self.blossom_parent[b] == UNMATCHED
+                        {
+                            match self.label[b] {
+                                1 => self.dual[b] = self.dual[b] + delta,
+                                2 => self.dual[b] = self.dual[b] - delta,
+                                _ => {}
+                            }
+                        }
+                    }
+
+                    match kind {
+                        1 => break,
+                        2 => {
+                            self.allowed[target] = true;
+                            let (i, j, _) = self.edges[target];
+                            let i = if self.label[self.in_blossom[i]] == 0 {
+                                j
+                            } else {
+                                i
+                            };
+                            self.queue.push(i);
+                        }
+                        3 => {
+                            self.allowed[target] = true;
+                            let (i, _, _) = self.edges[target];
+                            self.queue.push(i);
+                        }
+                        _ => self.expand_blossom(target, false),
+                    }
+                }
+
+                if !augmented {
+                    break;
+                }
+                for b in n..2 * n {
+                    if self.blossom_parent[b] == UNMATCHED
+                        && self.blossom_base[b] != UNMATCHED
+                        && self.label[b] == 1
+                        && self.dual[b] == zero
+                    {
+                        self.expand_blossom(b, true);
+                    }
+                }
+            }
+
+            self.mate
+                .iter()
+                .map(|&p| {
+                    if p == UNMATCHED {
+                        UNMATCHED
+                    } else {
+                        self.endpoint[p]
+                    }
+                })
+                .collect()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::super::test_support::lcg;
+        use super::*;
+
+        fn staffing() -> Graph<&'static str, (), ()> {
+            let mut graph = Graph::new();
+            for (worker, job) in [
+                ("w1", "j1"),
+                ("w1", "j2"),
+                ("w2", "j1"),
+                ("w3", "j2"),
+                ("w3", "j3"),
+                ("w3", "j4"),
+                ("w4", "j2"),
+            ] {
+                graph.add_edge(worker, job, ());
+            }
+            graph
+        }
+
+        fn assert_odd_cycle(graph: &Graph<&'static str, (), ()>, cycle: &[&'static str]) {
+            assert_eq!(cycle.len() % 2, 1);
+            for (position, from) in cycle.iter().enumerate() {
+                let to = &cycle[(position + 1) % cycle.len()];
+                assert!(graph.get_edge(from, to).is_some() || graph.get_edge(to, from).is_some());
+            }
+        }
+
+        #[test]
+        fn is_bipartite_colors_edges_apart_or_finds_an_odd_cycle() {
+            let mut graph = staffing();
+            let colors = is_bipartite(&graph).unwrap();
+            for (from, edges) in graph.iter_edges() {
+                for (to, _) in edges.iter() {
+                    assert_ne!(colors[from], colors[to]);
+                }
+            }
+
+            graph.add_edge("j3", "j4", ());
+            let OddCycle(cycle) = is_bipartite(&graph).unwrap_err();
+            assert_odd_cycle(&graph, &cycle);
+            let OddCycle(cycle) = maximum_bipartite_matching(&graph).unwrap_err();
+            assert_odd_cycle(&graph, &cycle);
+        }
+
+        #[test]
+        fn bipartite_matching_comes_with_a_minimum_vertex_cover() {
+            let graph = staffing();
+            let matching = maximum_bipartite_matching(&graph).unwrap();
+            assert_eq!(matching.len(), 3);
+            assert!(!matching.is_perfect());
+            for (a, b) in matching.pairs.iter() {
+                assert!(graph.get_edge(a, b).is_some() || graph.get_edge(b, a).is_some());
+                assert_eq!(matching.mate(a), Some(b));
+                assert_eq!(matching.mate(b), Some(a));
+            }
+
+            assert_eq!(matching.vertex_cover.len(), matching.len());
+            for (from, edges) in graph.iter_edges() {
+                for (to, _) in edges.iter() {
+                    assert!(
+                        matching.vertex_cover.contains(from) || matching.vertex_cover.contains(to)
+                    );
+                }
+            }
+
+            let mut square: Graph<u32, (), ()> = Graph::new();
+            square.push_undirected_edge(0, 1, ());
+            square.push_undirected_edge(1, 2, ());
+            square.push_undirected_edge(2, 3, ());
+            square.push_undirected_edge(3, 0, ());
+            assert!(maximum_bipartite_matching(&square).unwrap().is_perfect());
+            assert!(maximum_bipartite_matching(&Graph::<u32>::new())
+                .unwrap()
+                .is_empty());
+        }
+
+        #[test]
+        fn assignment_spans_components_in_any_orientation() {
+            for _ in 0..50 {
+                let mut graph: Graph<&str, (), i32> = Graph::new();
+                graph.push_undirected_edge("J1", "W1", 4);
+                graph.push_undirected_edge("J1", "W2", 1);
+                graph.add_edge("W3", "J2", 2);
+                graph.add_edge("W4", "J2", 3);
+                let assignment = min_cost_assignment(&graph, |cost| *cost).unwrap();
+                assert_eq!(assignment.total, 3);
+                let mut pairs = assignment.pairs;
+                pairs.sort();
+                assert_eq!(pairs, vec![("J1", "W2"), ("W3", "J2")]);
+            }
+        }
+
+        fn sorted_pairs(assignment: &Assignment<Slot, i32>) -> Vec<(Slot, Slot)> {
+            let mut pairs = assignment.pairs.clone();
+            pairs.sort();
+            pairs
+        }
+
+        #[test]
+        fn cost_matrices_get_their_optimal_assignments() {
+            let graph = from_cost_matrix(&[vec![4, 1, 3], vec![2, 0, 5], vec![3, 2, 2]]);
+            let cheapest = min_cost_assignment(&graph, |cost| *cost).unwrap();
+            assert_eq!(cheapest.total, 5);
+            assert_eq!(
+                sorted_pairs(&cheapest),
+                vec![
+                    (Slot::Row(0), Slot::Column(1)),
+                    (Slot::Row(1), Slot::Column(0)),
+                    (Slot::Row(2), Slot::Column(2)),
+                ]
+            );
+            let heaviest = max_weight_assignment(&graph, |weight| *weight).unwrap();
+            assert_eq!(heaviest.total, 11);
+            assert_eq!(
+                sorted_pairs(&heaviest),
+                vec![
+                    (Slot::Row(0), Slot::Column(0)),
+                    (Slot::Row(1), Slot::Column(2)),
+                    (Slot::Row(2), Slot::Column(1)),
+                ]
+            );
+        }
+
+        #[test]
+        fn rectangular_matrices_assign_the_smaller_side() {
+            let graph = from_cost_matrix(&[vec![1, 2, 3], vec![3, 1, 2]]);
+            let assignment = min_cost_assignment(&graph, |cost| *cost).unwrap();
+            assert_eq!(assignment.total, 2);
+            assert_eq!(
+                sorted_pairs(&assignment),
+                vec![
+                    (Slot::Row(0), Slot::Column(0)),
+                    (Slot::Row(1), Slot::Column(1)),
+                ]
+            );
+        }
+
+        #[test]
+        fn assignment_fails_without_a_covering_matching() {
+            let mut graph: Graph<char, (), i32> = Graph::new();
+            for (from, to) in [('a', 'x'), ('b', 'x'), ('c', 'x'), ('c', 'y'), ('c', 'z')] {
+                graph.add_edge(from, to, 1);
+            }
+            assert_eq!(
+                min_cost_assignment(&graph, |cost| *cost).unwrap_err(),
+                "No assignment covers every node of the smaller side."
+            );
+
+            graph.add_edge('a', 'b', 1);
+            assert_eq!(
+                max_weight_assignment(&graph, |weight| *weight).unwrap_err(),
+                "Graph is not bipartite."
+            );
+        }
+
+        fn weighted(edges: &[(u32, u32, i64)]) -> Graph<u32, (), i64> {
+            let mut graph = Graph::new();
+            for (from, to, weight) in edges.iter() {
+                graph.add_edge(*from, *to, *weight);
+            }
+            graph
+        }
+
+        fn assert_valid(graph: &Graph<u32, (), i64>, matching: &Matching<u32>) {
+            let mut seen = HashSet::new();
+            for (a, b) in matching.pairs.iter() {
+                assert!(graph.get_edge(a, b).is_some() || graph.get_edge(b, a).is_some());
+                assert!(seen.insert(*a) && seen.insert(*b));
+                assert_eq!(matching.mate(a), Some(b));
+            }
+        }
+
+        // Best total over every matching, trying each edge in or out.
+        fn brute_force(
+            edges: &[(u32, u32, i64)],
+            used: &mut Vec<u32>,
+            max_cardinality: bool,
+        ) -> (usize, i64) {
+            let Some(((a, b, weight), rest)) = edges.split_first() else {
+                return (0, 0);
+            };
+            let mut best = brute_force(rest, used, max_cardinality);
+            if !used.contains(a) && !used.contains(b) {
+                used.extend([*a, *b]);
+                let (count, total) = brute_force(rest, used, max_cardinality);
+                used.truncate(used.len() - 2);
+                let candidate = (count + 1, total + weight);
+                let better = if max_cardinality {
+                    candidate > best
+                } else {
+                    candidate.1 > best.1
+                };
+                if better {
+                    best = candidate;
+                }
+            }
+            best
+        }
+
+        #[test]
+        fn maximum_matching_shrinks_odd_cycles() {
+            let graph = weighted(&[
+                (0, 1, 1),
+                (1, 2, 1),
+                (2, 3, 1),
+                (3, 4, 1),
+                (4, 0, 1),
+                (5, 0, 1),
+            ]);
+            let matching = maximum_matching(&graph);
+            assert_valid(&graph, &matching);
+            assert!(matching.is_perfect());
+            assert_eq!(matching.mate(&5), Some(&0));
+
+            let path = weighted(&[(0, 1, 1), (1, 2, 1), (2, 3, 1), (3, 4, 1)]);
+            let matching = maximum_matching(&path);
+            assert_valid(&path, &matching);
+            assert_eq!(matching.len(), 2);
+            assert!(!matching.is_perfect());
+            assert!(maximum_matching(&Graph::<u32>::new()).is_empty());
+        }
+
+        #[test]
+        fn max_weight_matching_handles_blossoms() {
+            let graph = weighted(&[(1, 2, 8), (1, 3, 9), (2, 3, 10), (3, 4, 7)]);
+            let (matching, total) = max_weight_matching(&graph, |weight| *weight, false);
+            assert_valid(&graph, &matching);
+            assert_eq!(total, 15);
+            assert_eq!(matching.mate(&3), Some(&4));
+
+            let graph = weighted(&[
+                (1, 2, 8),
+                (1, 3, 9),
+                (2, 3, 10),
+                (3, 4, 7),
+                (1, 6, 5),
+                (4, 5, 6),
+            ]);
+            let (matching, total) = max_weight_matching(&graph, |weight| *weight, false);
+            assert_eq!(total, 21);
+            assert_eq!(matching.mate(&1), Some(&6));
+            assert_eq!(matching.mate(&2), Some(&3));
+
+            let nested = weighted(&[
+                (1, 2, 9),
+                (1, 3, 9),
+                (2, 3, 10),
+                (2, 4, 8),
+                (3, 5, 8),
+                (4, 5, 10),
+                (5, 6, 6),
+            ]);
+            let (matching, total) = max_weight_matching(&nested, |weight| *weight, false);
+            assert_valid(&nested, &matching);
+            assert_eq!(total, 23);
+        }
+
+        #[test]
+        fn max_cardinality_trades_weight_for_pairs() {
+            let graph = weighted(&[(0, 1, 1), (1, 2, 3), (2, 3, 1)]);
+            let (matching, total) = max_weight_matching(&graph, |weight| *weight, false);
+            assert_eq!((matching.len(), total), (1, 3));
+            let (matching, total) = max_weight_matching(&graph, |weight| *weight, true);
+            assert_eq!((matching.len(), total), (2, 2));
+
+            let negative = weighted(&[(0, 1, -1)]);
+            assert!(max_weight_matching(&negative, |weight| *weight, false)
+                .0
+                .is_empty());
+            assert_eq!(max_weight_matching(&negative, |weight| *weight, true).1, -1);
+        }
+
+        #[test]
+        fn max_weight_matching_agrees_with_brute_force() {
+            let mut next = lcg(7);
+            for _ in 0..200 {
+                let mut edges = vec![];
+                for a in 0..7 {
+                    for b in a + 1..7 {
+                        if next().is_multiple_of(3) {
+                            edges.push((a, b, (next() % 20) as i64));
+                        }
+                    }
+                }
+                let graph = weighted(&edges);
+                for max_cardinality in [false, true] {
+                    let (matching, total) =
+                        max_weight_matching(&graph, |weight| *weight, max_cardinality);
+                    assert_valid(&graph, &matching);
+                    let (count, best) = brute_force(&edges, &mut vec![], max_cardinality);
+                    assert_eq!(total, best);
+                    if max_cardinality {
+                        assert_eq!(matching.len(), count);
+                    }
+                }
+                let (count, _) = brute_force(&edges, &mut vec![], true);
+                assert_eq!(maximum_matching(&graph).len(), count);
+            }
+        }
+    }
+}
+
+pub mod biconnected {
+    use super::graph::Graph;
+    use super::*;
+
+    const UNVISITED: usize = usize::MAX;
+
+    #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+    pub enum BlockCutNode<Nid> {
+        Block(usize),
+        Cut(Nid),
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct Biconnectivity<Nid> {
+        pub articulation_points: Vec<Nid>,
+        pub bridges: Vec<(Nid, Nid)>,
+        pub components: Vec<Vec<(Nid, Nid)>>,
+    }
+
+    impl<Nid> Biconnectivity<Nid>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        pub fn block_cut_tree(&self) -> Graph<BlockCutNode<Nid>, (), ()> {
+            let cuts: HashSet<&Nid> = self.articulation_points.iter().collect();
+            let mut tree = Graph::new();
+            for cut in self.articulation_points.iter() {
+                tree.insert_node(BlockCutNode::Cut(cut.clone()), ());
+            }
+            for (block, edges) in self.components.iter().enumerate() {
+                tree.insert_node(BlockCutNode::Block(block), ());
+                let mut linked: HashSet<&Nid> = HashSet::new();
+                for (from, to) in edges.iter() {
+                    for node_id in [from, to] {
+                        if cuts.contains(node_id) && linked.insert(node_id) {
+                            tree.push_undirected_edge(
+                                BlockCutNode::Block(block),
+                                BlockCutNode::Cut(node_id.clone()),
+                                (),
+                            );
+                        }
+                    }
+                }
+            }
+            tree
+        }
+    }
+
+    pub fn articulation_points<Nid, N, E>(graph: &Graph<Nid, N, E>) -> Vec<Nid>
+    where
+        Nid: Hash + Eq + Clone,
+        E: PartialEq,
+    {
+        biconnected_components(graph).articulation_points
+    }
+
+    pub fn bridges<Nid, N, E>(graph: &Graph<Nid, N, E>) -> Vec<(Nid, Nid)>
+    where
+        Nid: Hash + Eq + Clone,
+        E: PartialEq,
+    {
+        biconnected_components(graph).bridges
+    }
+
+    pub fn biconnected_components<Nid, N, E>(graph: &Graph<Nid, N, E>) -> Biconnectivity<Nid>
+    where
+        Nid: Hash + Eq + Clone,
+        E: PartialEq,
+    {
+        let (node_ids, index) = graph.index_nodes();
+        let count = node_ids.len();
+        let mut ends: Vec<(usize, usize)> = vec![];
+        let mut incident: Vec<Vec<(usize, usize)>> = vec![vec![]; count];
+        for (from, to, _) in graph.undirected_edges() {
+            let (from, to) = (index[from], index[to]);
+            if from == to {
+                continue;
+            }
+            incident[from].push((to, ends.len()));
+            incident[to].push((from, ends.len()));
+            ends.push((from, to));
+        }
+        let pair = |edge: usize| {
+            let (from, to) = ends[edge];
+            (node_ids[from].clone(), node_ids[to].clone())
+        };
+
+        let mut order = vec![UNVISITED; count];
+        let mut low = vec![0; count];
+        let mut is_cut = vec![false; count];
+        let mut next_order = 0;
+        let mut edge_stack: Vec<usize> = vec![];
+        let mut bridges = vec![];
+        let mut components = vec![];
+
+        for root in 0..count {
+            if order[root] != UNVISITED {
+                continue;
+            }
+            order[root] = next_order;
+            low[root] = next_order;
+            next_order += 1;
+            let mut root_children = 0;
+            // (node, edge it was entered by, next incident position)
+            let mut calls: Vec<(usize, usize, usize)> = vec![(root, UNVISITED, 0)];
+
+            while let Some((node, via, position)) = calls.last_mut() {
+                let (node, via) = (*node, *via);
+                if let Some(&(next, edge)) = incident[node].get(*position) {
+                    *position += 1;
+                    if edge == via {
+                        continue;
+                    }
+                    if order[next] == UNVISITED {
+                        order[next] = next_order;
+                        low[next] = next_order;
+                        next_order += 1;
+                        edge_stack.push(edge);
+                        if node == root {
+                            root_children += 1;
+                        }
+                        calls.push((next, edge, 0));
+                    } else if order[next] < order[node] {
+                        edge_stack.push(edge);
+                        low[node] = low[node].min(order[next]);
+                    }
+                    continue;
+                }
+
+                calls.pop();
+                let parent = match calls.last() {
+                    Some(&(parent, _, _)) => parent,
+                    None => break,
+                };
+                low[parent] = low[parent].min(low[node]);
+                if low[node] > order[parent] {
+                    bridges.push(pair(via));
+                }
+                if low[node] >= order[parent] {
+                    if parent != root {
+                        is_cut[parent] = true;
+                    }
+                    let mut component = vec![];
+                    while let Some(edge) = edge_stack.pop() {
+                        component.push(pair(edge));
+                        if edge == via {
+                            break;
+                        }
+                    }
+                    components.push(component);
+                }
+            }
+            if root_children > 1 {
+                is_cut[root] = true;
+            }
+        }
+
+        Biconnectivity {
+            articulation_points: (0..count)
+                .filter(|node| is_cut[*node])
+                .map(|node| node_ids[node].clone())
+                .collect(),
+            bridges,
+            components,
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn bow_tie() -> Graph<u32, (), u32> {
+            let mut graph = Graph::new();
+            for (from, to) in [
+                (0, 1),
+                (1, 2),
+                (2, 0),
+                (2, 3),
+                (3, 4),
+                (4, 2),
+                (4, 5),
+                (5, 6),
+            ] {
+                graph.push_undirected_edge(from, to, 0);
+            }
+            graph.push_undirected_edge(7, 8, 1);
+            graph.push_undirected_edge(7, 8, 2);
+            graph.push_undirected_edge(0, 0, 0);
+            graph
+        }
+
+        fn normalized(pairs: &[(u32, u32)]) -> Vec<(u32, u32)> {
+            let mut pairs: Vec<(u32, u32)> = pairs
+                .iter()
+                .map(|(from, to)| (*from.min(to), *from.max(to)))
+                .collect();
+            pairs.sort();
+            pairs
+        }
+
+        #[test]
+        fn cut_nodes_and_bridges_split_the_graph() {
+            let graph = bow_tie();
+            let mut points = articulation_points(&graph);
+            points.sort();
+            assert_eq!(points, vec![2, 4, 5]);
+            assert_eq!(normalized(&bridges(&graph)), vec![(4, 5), (5, 6)]);
+
+            let mut components: Vec<Vec<(u32, u32)>> = biconnected_components(&graph)
+                .components
+                .iter()
+                .map(|component| normalized(component))
+                .collect();
+            components.sort();
+            assert_eq!(
+                components,
+                vec![
+                    vec![(0, 1), (0, 2), (1, 2)],
+                    vec![(2, 3), (2, 4), (3, 4)],
+                    vec![(4, 5)],
+                    vec![(5, 6)],
+                    vec![(7, 8), (7, 8)],
+                ]
+            );
+        }
+
+        #[test]
+        fn block_cut_tree_links_blocks_through_cut_nodes() {
+            let biconnectivity = biconnected_components(&bow_tie());
+            let tree = biconnectivity.block_cut_tree();
+            assert_eq!(tree.node_ids().len(), 8);
+            assert_eq!(tree.undirected_edges().len(), 6);
+
+            let block_of = |edge: (u32, u32)| {
+                let block = biconnectivity
+                    .components
+                    .iter()
+                    .position(|component| normalized(component).contains(&edge))
+                    .unwrap();
+                BlockCutNode::Block(block)
+            };
+            let around_two: HashSet<&BlockCutNode<u32>> = tree
+                .get_adjacent(&BlockCutNode::Cut(2))
+                .into_iter()
+                .collect();
+            assert_eq!(
+                around_two,
+                HashSet::from([&block_of((0, 1)), &block_of((2, 3))])
+            );
+            assert!(tree.edges_from(&block_of((7, 8))).is_none());
+        }
+
+        #[test]
+        fn deep_paths_do_not_overflow_the_stack() {
+            let mut graph: Graph<u32, (), ()> = Graph::new();
+            for node_id in 0..20_000 {
+                graph.push_undirected_edge(node_id, node_id + 1, ());
+            }
+            assert_eq!(bridges(&graph).len(), 20_000);
+            assert_eq!(articulation_points(&graph).len(), 19_999);
+        }
+    }
+}
+
+pub mod euler {
+    use super::graph::Graph;
+    use super::*;
+    use std::fmt;
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum EulerError<Nid> {
+        // Nodes whose out-degree minus in-degree rules out the requested walk.
+        Imbalanced(Vec<(Nid, isize)>),
+        OddDegree(Vec<Nid>),
+        Disconnected(Vec<Nid>),
+    }
+
+    impl<Nid: Debug> fmt::Display for EulerError<Nid> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                EulerError::Imbalanced(nodes) => {
+                    write!(f, "Out-degree and in-degree differ at")?;
+                    for (node_id, surplus) in nodes.iter() {
+                        write!(f, " {:?} (out - in = {})", node_id, surplus)?;
+                    }
+                    Ok(())
+                }
+                EulerError::OddDegree(nodes) => {
+                    write!(f, "{} nodes have odd degree: {:?}", nodes.len(), nodes)
+                }
+                EulerError::Disconnected(nodes) => write!(
+                    f,
+                    "Edges are split between several components, unreachable: {:?}",
+                    nodes
+                ),
+            }
+        }
+    }
+
+    pub fn eulerian_circuit<Nid, N, E>(
+        graph: &Graph<Nid, N, E>,
+    ) -> Result<Vec<Nid>, EulerError<Nid>>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        directed_walk(graph, true)
+    }
+
+    pub fn eulerian_path<Nid, N, E>(graph: &Graph<Nid, N, E>) -> Result<Vec<Nid>, EulerError<Nid>>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        directed_walk(graph, false)
+    }
+
+    pub fn eulerian_circuit_undirected<Nid, N, E>(
+        graph: &Graph<Nid, N, E>,
+    ) -> Result<Vec<Nid>, EulerError<Nid>>
+    where
+        Nid: Hash + Eq + Clone,
+        E: PartialEq,
+    {
+        undirected_walk(graph, true)
+    }
+
+    pub fn eulerian_path_undirected<Nid, N, E>(
+        graph: &Graph<Nid, N, E>,
+    ) -> Result<Vec<Nid>, EulerError<Nid>>
+    where
+        Nid: Hash + Eq + Clone,
+        E: PartialEq,
+    {
+        undirected_walk(graph, false)
+    }
+
+    fn directed_walk<Nid, N, E>(
+        graph: &Graph<Nid, N, E>,
+        closed: bool,
+    ) -> Result<Vec<Nid>, EulerError<Nid>>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        let (node_ids, index) = graph.index_nodes();
+        let successors = graph.successor_lists(&index);
+        let count = node_ids.len();
+        let mut surplus = vec![0isize; count];
+        for (node, nexts) in successors.iter().enumerate() {
+            surplus[node] += nexts.len() as isize;
+            for &next in nexts.iter() {
+                surplus[next] -= 1;
+            }
+        }
+
+        let imbalanced: Vec<usize> = (0..count).filter(|node| surplus[*node] != 0).collect();
+        let start = match imbalanced.as_slice() {
+            [] => (0..count).find(|node| !successors[*node].is_empty()),
+            [a, b] if !closed && surplus[*a] == 1 && surplus[*b] == -1 => Some(*a),
+            [a, b] if !closed && surplus[*a] == -1 && surplus[*b] == 1 => Some(*b),
+            _ => {
+                return Err(EulerError::Imbalanced(
+                    imbalanced
+                        .into_iter()
+                        .map(|node| (node_ids[node].clone(), surplus[node]))
+                        .collect(),
+                ))
+            }
+        };
+        let start = match start {
+            Some(start) => start,
+            None => return Ok(vec![]),
+        };
+
+        let mut position = vec![0; count];
+        let mut stack = vec![start];
+        let mut walk = vec![];
+        while let Some(&node) = stack.last() {
+            match successors[node].get(position[node]) {
+                Some(&next) => {
+                    position[node] += 1;
+                    stack.push(next);
+                }
+                None => walk.push(stack.pop().unwrap()),
+            }
+        }
+
+        let unused: Vec<Nid> = (0..count)
+            .filter(|node| position[*node] < successors[*node].len())
+            .map(|node| node_ids[node].clone())
+            .collect();
+        if !unused.is_empty() {
+            return Err(EulerError::Disconnected(unused));
+        }
+        walk.reverse();
+        Ok(walk
+            .into_iter()
+            .map(|node| node_ids[node].clone())
+            .collect())
+    }
+
+    fn undirected_walk<Nid, N, E>(
+        graph: &Graph<Nid, N, E>,
+        closed: bool,
+    ) -> Result<Vec<Nid>, EulerError<Nid>>
+    where
+        Nid: Hash + Eq + Clone,
+        E: PartialEq,
+    {
+        let (node_ids, index) = graph.index_nodes();
+        let count = node_ids.len();
+        let mut incident: Vec<Vec<(usize, usize)>> = vec![vec![]; count];
+        let mut edge_count = 0;
+        for (from, to, _) in graph.undirected_edges() {
+            let (from, to) = (index[from], index[to]);
+            incident[from].push((to, edge_count));
+            incident[to].push((from, edge_count));
+            edge_count += 1;
+        }
+
+        let odd: Vec<usize> = (0..count)
+            .filter(|node| incident[*node].len() % 2 == 1)
+            .collect();
+        let start = match odd.as_slice() {
+            [] => (0..count).find(|node| !incident[*node].is_empty()),
+            [a, _] if !closed => Some(*a),
+            _ => {
+                return Err(EulerError::OddDegree(
+                    odd.into_iter().map(|node| node_ids[node].clone()).collect(),
+                ))
+            }
+        };
+        let start = match start {
+            Some(start) => start,
+            None => return Ok(vec![]),
+        };
+
+        let mut used = vec![false; edge_count];
+        let mut position = vec![0; count];
+        let mut stack = vec![start];
+        let mut walk = vec![];
+        while let Some(&node) = stack.last() {
+            while position[node] < incident[node].len() && used[incident[node][position[node]].1] {
+                position[node] += 1;
+            }
+            match incident[node].get(position[node]) {
+                Some(&(next, edge)) => {
+                    used[edge] = true;
+                    stack.push(next);
+                }
+                None => walk.push(stack.pop().unwrap()),
+            }
+        }
+
+        if walk.len() != edge_count + 1 {
+            let unused: Vec<Nid> = (0..count)
+                .filter(|node| incident[*node].iter().any(|(_, edge)| !used[*edge]))
+                .map(|node| node_ids[node].clone())
+                .collect();
+            return Err(EulerError::Disconnected(unused));
+        }
+        walk.reverse();
+        Ok(walk
+            .into_iter()
+            .map(|node| node_ids[node].clone())
+            .collect())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::super::test_support::{directed, undirected};
+        use super::*;
+
+        fn assert_directed_walk(graph: &Graph<u32, (), ()>, walk: &[u32]) {
+            let mut unused: Vec<(u32, u32)> = graph
+                .iter_edges()
+                .flat_map(|(from, edges)| edges.iter().map(move |(to, _)| (*from, *to)))
+                .collect();
+            assert_eq!(walk.len(), unused.len() + 1);
+            for step in walk.windows(2) {
+                let position = unused.iter().position(|edge| *edge == (step[0], step[1]));
+                unused.swap_remove(position.unwrap());
+            }
+        }
+
+        fn assert_undirected_walk(graph: &Graph<u32, (), ()>, walk: &[u32]) {
+            let mut unused: Vec<(u32, u32)> = graph
+                .undirected_edges()
+                .into_iter()
+                .map(|(from, to, _)| (*from.min(to), *from.max(to)))
+                .collect();
+            assert_eq!(walk.len(), unused.len() + 1);
+            for step in walk.windows(2) {
+                let edge = (step[0].min(step[1]), step[0].max(step[1]));
+                let position = unused.iter().position(|unused| *unused == edge);
+                unused.swap_remove(position.unwrap());
+            }
+        }
+
+        #[test]
+        fn directed_walks_use_every_edge_once() {
+            let graph = directed(&[(0, 1), (1, 2), (2, 0), (0, 3), (3, 4), (4, 0), (4, 4)]);
+            let circuit = eulerian_circuit(&graph).unwrap();
+            assert_directed_walk(&graph, &circuit);
+            assert_eq!(circuit.first(), circuit.last());
+
+            let graph = directed(&[(0, 1), (1, 2), (2, 0), (0, 3)]);
+            let path = eulerian_path(&graph).unwrap();
+            assert_directed_walk(&graph, &path);
+            assert_eq!((path[0], path[4]), (0, 3));
+
+            assert_eq!(eulerian_circuit(&Graph::<u32>::new()), Ok(vec![]));
+        }
+
+        #[test]
+        fn directed_walks_report_imbalance_and_disconnection() {
+            let mut graph = directed(&[(0, 1), (1, 2), (2, 0), (0, 3)]);
+            match eulerian_circuit(&graph) {
+                Err(EulerError::Imbalanced(mut nodes)) => {
+                    nodes.sort();
+                    assert_eq!(nodes, vec![(0, 1), (3, -1)]);
+                }
+                other => panic!("expected an imbalance, got {:?}", other),
+            }
+            graph.add_edge(0, 4, ());
+            assert!(matches!(
+                eulerian_path(&graph),
+                Err(EulerError::Imbalanced(_))
+            ));
+
+            let graph = directed(&[(0, 1), (1, 0), (2, 3), (3, 2)]);
+            assert!(matches!(
+                eulerian_circuit(&graph),
+                Err(EulerError::Disconnected(_))
+            ));
+        }
+
+        #[test]
+        fn undirected_walks_use_every_edge_once() {
+            let house = undirected(&[(0, 1), (1, 2), (2, 3), (3, 0), (2, 4), (4, 3)]);
+            let path = eulerian_path_undirected(&house).unwrap();
+            assert_undirected_walk(&house, &path);
+            let mut ends = [path[0], path[6]];
+            ends.sort();
+            assert_eq!(ends, [2, 3]);
+            match eulerian_circuit_undirected(&house) {
+                Err(EulerError::OddDegree(mut nodes)) => {
+                    nodes.sort();
+                    assert_eq!(nodes, vec![2, 3]);
+                }
+                other => panic!("expected odd degrees, got {:?}", other),
+            }
+
+            let mut bow_tie = undirected(&[(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 2)]);
+            bow_tie.push_undirected_edge(1, 1, ());
+            let circuit = eulerian_circuit_undirected(&bow_tie).unwrap();
+            assert_undirected_walk(&bow_tie, &circuit);
+            assert_eq!(circuit.first(), circuit.last());
+        }
+
+        #[test]
+        fn undirected_walks_report_disconnection() {
+            let triangles = undirected(&[(0, 1), (1, 2), (2, 0), (3, 4), (4, 5), (5, 3)]);
+            let error = eulerian_circuit_undirected(&triangles).unwrap_err();
+            assert!(matches!(error, EulerError::Disconnected(ref nodes) if nodes.len() == 3));
+            assert!(error
+                .to_string()
+                .starts_with("Edges are split between several components"));
+            assert_eq!(
+                EulerError::OddDegree(vec![2, 3]).to_string(),
+                "2 nodes have odd degree: [2, 3]"
+            );
+        }
+    }
+}
+
+pub mod postman {
+    use super::euler::{eulerian_circuit, eulerian_circuit_undirected};
+    use super::flow::min_cost_flow;
+    use super::graph::Graph;
+    use super::matching::max_weight_matching;
+    use super::scc::tarjan_scc;
+    use super::shortest_path::{dijkstra, Measure};
+    use super::*;
+    use std::ops::{Div, Mul, Sub};
+
+    #[derive(Clone, Debug)]
+    pub struct PostmanRoute<Nid, K> {
+        pub route: Vec<Nid>,
+        pub cost: K,
+    }
+
+    // Edge weights are expected to be non-negative, the repeated stretches are
+    // found with Dijkstra.
+    pub fn chinese_postman_undirected<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        mut weight: F,
+    ) -> Result<PostmanRoute<Nid, K>, &'static str>
+    where
+        Nid: Hash + Eq + Clone,
+        E: PartialEq,
+        K: Measure + Sub<Output = K> + Div<Output = K> + From<u8>,
+        F: FnMut(&E) -> K,
+    {
+        let zero = K::default();
+        let (node_ids, index) = graph.index_nodes();
+        let mut degree = vec![0; node_ids.len()];
+        let mut weighted: Graph<usize, (), K> = Graph::new();
+        // Every traversal gets its own id so parallel copies stay distinct.
+        let mut traversals: Graph<usize, (), usize> = Graph::new();
+        let mut traversal_count = 0;
+        let mut cost = zero;
+        for (from, to, edge) in graph.undirected_edges() {
+            let (from, to) = (index[from], index[to]);
+            let value = weight(edge);
+            cost = cost + value;
+            weighted.push_undirected_edge(from, to, value);
+            traversals.push_undirected_edge(from, to, traversal_count);
+            traversal_count += 1;
+            degree[from] += 1;
+            degree[to] += 1;
+        }
+
+        let odd: Vec<usize> = (0..node_ids.len())
+            .filter(|node| degree[*node] % 2 == 1)
+            .collect();
+        if !odd.is_empty() {
+            let paths: Vec<_> = odd
+                .iter()
+                .map(|start| dijkstra(&weighted, *start, |value| *value))
+                .collect();
+            let mut longest = zero;
+            for (i, paths) in paths.iter().enumerate() {
+                for target in odd[i + 1..].iter() {
+                    let distance = paths
+                        .distance(target)
+                        .ok_or("Edges are split between several components.")?;
+                    if longest < distance {
+                        longest = distance;
+                    }
+                }
+            }
+            // Maximising `longest - distance` over perfect matchings minimises the
+            // total distance of the repeated stretches.
+            let mut pairing: Graph<usize, (), K> = Graph::new();
+            for (i, paths) in paths.iter().enumerate() {
+                for (j, target) in odd.iter().enumerate().skip(i + 1) {
+                    pairing.add_edge(i, j, longest - paths.distance(target).unwrap());
+                }
+            }
+            let (matching, _) = max_weight_matching(&pairing, |value| *value, true);
+            for (i, j) in matching.pairs {
+                cost = cost + paths[i].distance(&odd[j]).unwrap();
+                let path = paths[i].path_to(&odd[j]).unwrap();
+                for step in path.windows(2) {
+                    traversals.push_undirected_edge(step[0], step[1], traversal_count);
+                    traversal_count += 1;
+                }
+            }
+        }
+
+        let route = eulerian_circuit_undirected(&traversals)
+            .map_err(|_| "Edges are split between several components.")?;
+        Ok(PostmanRoute {
+            route: route
+                .into_iter()
+                .map(|node| node_ids[node].clone())
+                .collect(),
+            cost,
+        })
+    }
+
+    // Edge weights are expected to be non-negative, every edge has to be reachable
+    // from every other one.
+    pub fn chinese_postman<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        mut weight: F,
+    ) -> Result<PostmanRoute<Nid, K>, &'static str>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure + Sub<Output = K> + Mul<Output = K> + From<u32>,
+        F: FnMut(&E) -> K,
+    {
+        let zero = K::default();
+        let (node_ids, index) = graph.index_nodes();
+        let count = node_ids.len();
+        let mut surplus = vec![0isize; count];
+        let mut traversals: Graph<usize, (), ()> = Graph::new();
+        let mut network: Graph<usize, (), (K, K)> = Graph::new();
+        let mut streets = vec![];
+        let mut cost = zero;
+        for (from, edges) in graph.iter_edges() {
+            for (to, edge) in edges.iter() {
+                let (from, to) = (index[from], index[to]);
+                let value = weight(edge);
+                cost = cost + value;
+                traversals.add_edge(from, to, ());
+                streets.push((from, to, value));
+                surplus[from] += 1;
+                surplus[to] -= 1;
+            }
+        }
+        if tarjan_scc(&traversals).len() > 1 {
+            return Err("Graph is not strongly connected.");
+        }
+
+        // Nodes entered more often than left have to send the extra walks out.
+        let (source, sink) = (count, count + 1);
+        let mut required_walks = 0;
+        for (node, surplus) in surplus.iter().enumerate() {
+            let walks = surplus.unsigned_abs() as u32;
+            let amount = K::from(walks);
+            if *surplus < 0 {
+                network.add_edge(source, node, (amount, zero));
+                required_walks += walks;
+            } else if *surplus > 0 {
+                network.add_edge(node, sink, (amount, zero));
+            }
+        }
+        let required = K::from(required_walks);
+        if required_walks > 0 {
+            for (from, to, value) in streets {
+                network.add_edge(from, to, (required, value));
+            }
+            let repeats = min_cost_flow(&network, &source, &sink, required, |edge| *edge)?;
+            cost = cost + repeats.cost;
+            for (from, edges) in network.iter_edges() {
+                for (position, (to, _)) in edges.iter().enumerate() {
+                    if *from == source || *to == sink {
+                        continue;
+                    }
+                    // Every unit of flow is one more walk along the street, so the
+                    // smallest whole count covering the flow is the number of repeats.
+                    let flow = repeats.flow_on(from, position);
+                    let (mut low, mut high) = (0, required_walks);
+                    while low < high {
+                        let middle = low + (high - low) / 2;
+                        if K::from(middle) < flow {
+                            low = middle + 1;
+                        } else {
+                            high = middle;
+                        }
+                    }
+                    for _ in 0..low {
+                        traversals.add_edge(*from, *to, ());
+                    }
+                }
+            }
+        }
+
+        let route =
+            eulerian_circuit(&traversals).map_err(|_| "Graph is not strongly connected.")?;
+        Ok(PostmanRoute {
+            route: route
+                .into_iter()
+                .map(|node| node_ids[node].clone())
+                .collect(),
+            cost,
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn route_cost(graph: &Graph<u32, (), i64>, route: &[u32], directed: bool) -> i64 {
+            route
+                .windows(2)
+                .map(|step| {
+                    let forward = graph.edges_from_to(&step[0], &step[1]).unwrap_or_default();
+                    let backward = if directed {
+                        vec![]
+                    } else {
+                        graph.edges_from_to(&step[1], &step[0]).unwrap_or_default()
+                    };
+                    **forward.iter().chain(backward.iter()).min().unwrap()
+                })
+                .sum()
+        }
+
+        #[test]
+        fn undirected_routes_repeat_the_cheapest_links() {
+            let mut house: Graph<u32, (), i64> = Graph::new();
+            for (from, to, weight) in [
+                (0, 1, 1),
+                (1, 2, 1),
+                (2, 3, 1),
+                (3, 0, 1),
+                (2, 4, 2),
+                (4, 3, 2),
+            ] {
+                house.push_undirected_edge(from, to, weight);
+            }
+            let postman = chinese_postman_undirected(&house, |weight| *weight).unwrap();
+            assert_eq!(postman.cost, 9);
+            assert_eq!(postman.route.len(), 8);
+            assert_eq!(postman.route.first(), postman.route.last());
+            assert_eq!(route_cost(&house, &postman.route, false), 9);
+            for (from, to, _) in house.undirected_edges() {
+                assert!(postman
+                    .route
+                    .windows(2)
+                    .any(|step| (step[0], step[1]) == (*from, *to)
+                        || (step[1], step[0]) == (*from, *to)));
+            }
+
+            let mut triangles: Graph<u32, (), i64> = Graph::new();
+            for (from, to) in [(0, 1), (1, 2), (2, 0), (3, 4), (4, 5), (5, 3)] {
+                triangles.push_undirected_edge(from, to, 1);
+            }
+            assert_eq!(
+                chinese_postman_undirected(&triangles, |weight| *weight).unwrap_err(),
+                "Edges are split between several components."
+            );
+        }
+
+        #[test]
+        fn directed_routes_balance_degrees_with_the_cheapest_paths() {
+            let mut graph: Graph<u32, (), i64> = Graph::new();
+            for (from, to, weight) in [(0, 1, 1), (1, 2, 1), (2, 0, 1), (1, 0, 5)] {
+                graph.add_edge(from, to, weight);
+            }
+            let postman = chinese_postman(&graph, |weight| *weight).unwrap();
+            assert_eq!(postman.cost, 9);
+            assert_eq!(postman.route.len(), 6);
+            assert_eq!(postman.route.first(), postman.route.last());
+            assert_eq!(route_cost(&graph, &postman.route, true), 9);
+
+            graph.add_edge(2, 3, 1);
+            assert_eq!(
+                chinese_postman(&graph, |weight| *weight).unwrap_err(),
+                "Graph is not strongly connected."
+            );
+        }
+
+        #[test]
+        fn directed_routes_accept_unsigned_and_fractional_weights() {
+            let mut graph: Graph<u32, (), u32> = Graph::new();
+            for _ in 0..3 {
+                graph.add_edge(0, 1, 1);
+            }
+            graph.add_edge(1, 0, 2);
+            let postman = chinese_postman(&graph, |weight| *weight).unwrap();
+            assert_eq!(postman.cost, 9);
+            assert_eq!(postman.route.len(), 7);
+            assert_eq!(postman.route.first(), postman.route.last());
+
+            let postman = chinese_postman(&graph, |weight| *weight as f64 / 2.0).unwrap();
+            assert_eq!(postman.cost, 4.5);
+            assert_eq!(postman.route.len(), 7);
+        }
+    }
+}
+
+pub mod coloring {
+    use super::graph::Graph;
+    use super::*;
+    use std::cmp::Reverse;
+
+    const UNCOLORED: usize = usize::MAX;
+
+    // Edge directions are ignored and self-loops cannot be respected by any
+    // coloring, so they are skipped.
+    fn simple_neighbours<Nid, N, E>(graph: &Graph<Nid, N, E>) -> (Vec<&Nid>, Vec<Vec<usize>>)
+    where
+        Nid: Hash + Eq,
+    {
+        let (node_ids, index) = graph.index_nodes();
+        let mut neighbours = graph.neighbour_lists(&index);
+        for (node, list) in neighbours.iter_mut().enumerate() {
+            list.retain(|other| *other != node);
+            list.sort_unstable();
+            list.dedup();
+        }
+        (node_ids, neighbours)
+    }
+
+    fn smallest_free_color(neighbours: &[usize], colors: &[usize]) -> usize {
+        let taken: HashSet<usize> = neighbours.iter().map(|other| colors[*other]).collect();
+        (0..).find(|color| !taken.contains(color)).unwrap()
+    }
+
+    fn into_coloring<Nid: Hash + Eq + Clone>(
+        node_ids: Vec<&Nid>,
+        colors: Vec<usize>,
+    ) -> HashMap<Nid, usize> {
+        node_ids.into_iter().cloned().zip(colors).collect()
+    }
+
+    // Nodes missing from `order` are colored after it, in no particular order.
+    pub fn greedy_coloring<Nid, N, E>(
+        graph: &Graph<Nid, N, E>,
+        order: &[Nid],
+    ) -> HashMap<Nid, usize>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        let (node_ids, neighbours) = simple_neighbours(graph);
+        let index: HashMap<&Nid, usize> = node_ids
+            .iter()
+            .enumerate()
+            .map(|(position, node_id)| (*node_id, position))
+            .collect();
+        let mut colors = vec![UNCOLORED; node_ids.len()];
+        let ordered = order
+            .iter()
+            .filter_map(|node_id| index.get(node_id).copied());
+        for node in ordered.chain(0..node_ids.len()) {
+            if colors[node] == UNCOLORED {
+                colors[node] = smallest_free_color(&neighbours[node], &colors);
+            }
+        }
+        into_coloring(node_ids, colors)
+    }
+
+    pub fn dsatur_coloring<Nid, N, E>(graph: &Graph<Nid, N, E>) -> HashMap<Nid, usize>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        let (node_ids, neighbours) = simple_neighbours(graph);
+        let colors = dsatur(&neighbours);
+        into_coloring(node_ids, colors)
+    }
+
+    fn dsatur(neighbours: &[Vec<usize>]) -> Vec<usize> {
+        let count = neighbours.len();
+        let mut colors = vec![UNCOLORED; count];
+        let mut adjacent_colors: Vec<HashSet<usize>> = vec![HashSet::new(); count];
+        // Entries go stale once a node is colored or its saturation grows.
+        let mut queue: BinaryHeap<(usize, usize, Reverse<usize>)> = (0..count)
+            .map(|node| (0, neighbours[node].len(), Reverse(node)))
+            .collect();
+        while let Some((saturation, _, Reverse(node))) = queue.pop() {
+            if colors[node] != UNCOLORED || saturation != adjacent_colors[node].len() {
+                continue;
+            }
+            let color = smallest_free_color(&neighbours[node], &colors);
+            colors[node] = color;
+            for other in neighbours[node].iter() {
+                if colors[*other] == UNCOLORED && adjacent_colors[*other].insert(color) {
+                    queue.push((
+                        adjacent_colors[*other].len(),
+                        neighbours[*other].len(),
+                        Reverse(*other),
+                    ));
+                }
+            }
+        }
+        colors
+    }
+
+    struct ExactColoring<'a> {
+        neighbours: &'a [Vec<usize>],
+        colors: Vec<usize>,
+        // conflicts[node][color] counts the neighbours of node painted in color.
+        conflicts: Vec<Vec<usize>>,
+        best: Vec<usize>,
+        best_count: usize,
+        lower_bound: usize,
+    }
+
+    impl ExactColoring<'_> {
+        fn saturation(&self, node: usize, used: usize) -> usize {
+            self.conflicts[node][..used]
+                .iter()
+                .filter(|count| **count > 0)
+                .count()
+        }
+
+        fn paint(&mut self, node: usize, color: usize) {
+            self.colors[node] = color;
+            for other in self.neighbours[node].iter() {
+                self.conflicts[*other][color] += 1;
+            }
+        }
+
+        fn erase(&mut self, node: usize) {
+            let color = self.colors[node];
+            self.colors[node] = UNCOLORED;
+            for other in self.neighbours[node].iter() {
+                self.conflicts[*other][color] -= 1;
+            }
+        }
+
+        // Branches on the most saturated node, opening a new color only while
+        // that still beats the best coloring found so far.
+        fn search(&mut self, remaining: usize, used: usize) {
+            if remaining == 0 {
+                self.best.clone_from(&self.colors);
+                self.best_count = used;
+                return;
+            }
+            let node = (0..self.colors.len())
+                .filter(|node| self.colors[*node] == UNCOLORED)
+                .max_by_key(|node| (self.saturation(*node, used), self.neighbours[*node].len()))
+                .unwrap();
+            for color in 0..=used {
+                if color >= self.best_count - 1 || self.best_count == self.lower_bound {
+                    break;
+                }
+                if self.conflicts[node][color] > 0 {
+                    continue;
+                }
+                self.paint(node, color);
+                self.search(remaining - 1, used.max(color + 1));
+                self.erase(node);
+            }
+        }
+    }
+
+    fn greedy_clique_size(neighbours: &[Vec<usize>]) -> usize {
+        let adjacent: Vec<HashSet<usize>> = neighbours
+            .iter()
+            .map(|list| list.iter().copied().collect())
+            .collect();
+        let mut largest = 0;
+        for (node, list) in neighbours.iter().enumerate() {
+            let mut candidates = list.clone();
+            candidates.sort_unstable_by_key(|other| Reverse(neighbours[*other].len()));
+            let mut clique = vec![node];
+            for candidate in candidates {
+                if clique
+                    .iter()
+                    .all(|member| adjacent[candidate].contains(member))
+                {
+                    clique.push(candidate);
+                }
+            }
+            largest = largest.max(clique.len());
+        }
+        largest
+    }
+
+    // Branch and bound over DSatur orderings; exponential in the worst case, so
+    // meant for small graphs only.
+    pub fn chromatic_coloring<Nid, N, E>(graph: &Graph<Nid, N, E>) -> HashMap<Nid, usize>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        let (node_ids, neighbours) = simple_neighbours(graph);
+        let count = node_ids.len();
+        let best = dsatur(&neighbours);
+        let best_count = best.iter().map(|color| color + 1).max().unwrap_or(0);
+        let mut exact = ExactColoring {
+            neighbours: &neighbours,
+            colors: vec![UNCOLORED; count],
+            conflicts: vec![vec![0; best_count]; count],
+            best,
+            best_count,
+            lower_bound: greedy_clique_size(&neighbours),
+        };
+        if exact.best_count > exact.lower_bound {
+            exact.search(count, 0);
+        }
+        into_coloring(node_ids, exact.best)
+    }
+
+    pub fn chromatic_number<Nid, N, E>(graph: &Graph<Nid, N, E>) -> usize
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        let coloring = chromatic_coloring(graph);
+        coloring.values().map(|color| color + 1).max().unwrap_or(0)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::super::test_support::undirected;
+        use super::*;
+
+        // Pairs `i` and `10 + j` are adjacent unless `i == j`.
+        fn crown(size: u32) -> Graph<u32, (), ()> {
+            let mut edges = vec![];
+            for i in 0..size {
+                for j in 0..size {
+                    if i != j {
+                        edges.push((i, 10 + j));
+                    }
+                }
+            }
+            undirected(&edges)
+        }
+
+        fn petersen() -> Graph<u32, (), ()> {
+            let mut edges = vec![];
+            for i in 0..5 {
+                edges.push((i, (i + 1) % 5));
+                edges.push((i, i + 5));
+                edges.push((i + 5, (i + 2) % 5 + 5));
+            }
+            undirected(&edges)
+        }
+
+        fn color_count(graph: &Graph<u32, (), ()>, coloring: &HashMap<u32, usize>) -> usize {
+            assert_eq!(coloring.len(), graph.node_ids().len());
+            for (from, edges) in graph.iter_edges() {
+                for (to, _) in edges.iter() {
+                    assert!(from == to || coloring[from] != coloring[to]);
+                }
+            }
+            let used: HashSet<usize> = coloring.values().copied().collect();
+            assert!(used.iter().all(|color| *color < used.len()));
+            used.len()
+        }
+
+        #[test]
+        fn greedy_coloring_follows_the_given_order() {
+            let graph = crown(4);
+            let interleaved: Vec<u32> = (0..4).flat_map(|i| [i, 10 + i]).collect();
+            let coloring = greedy_coloring(&graph, &interleaved);
+            assert_eq!(color_count(&graph, &coloring), 4);
+            let sides: Vec<u32> = (0..4).chain(10..14).collect();
+            assert_eq!(color_count(&graph, &greedy_coloring(&graph, &sides)), 2);
+
+            let path = undirected(&[(0, 1), (1, 2), (2, 3)]);
+            let coloring = greedy_coloring(&path, &[1]);
+            assert_eq!(coloring[&1], 0);
+            assert_eq!(color_count(&path, &coloring), 2);
+        }
+
+        #[test]
+        fn dsatur_colors_bipartite_graphs_with_two_colors() {
+            let graph = crown(4);
+            assert_eq!(color_count(&graph, &dsatur_coloring(&graph)), 2);
+            let petersen = petersen();
+            assert_eq!(color_count(&petersen, &dsatur_coloring(&petersen)), 3);
+            assert!(dsatur_coloring(&Graph::<u32>::new()).is_empty());
+        }
+
+        #[test]
+        fn chromatic_coloring_is_optimal() {
+            let petersen = petersen();
+            assert_eq!(color_count(&petersen, &chromatic_coloring(&petersen)), 3);
+            assert_eq!(chromatic_number(&petersen), 3);
+
+            let mut wheel = undirected(&[(0, 1), (1, 2), (2, 3), (3, 4), (4, 0)]);
+            assert_eq!(chromatic_number(&wheel), 3);
+            for spoke in 0..5 {
+                wheel.add_edge(5, spoke, ());
+            }
+            assert_eq!(chromatic_number(&wheel), 4);
+            assert_eq!(color_count(&wheel, &chromatic_coloring(&wheel)), 4);
+
+            let mut lonely: Graph<u32, (), ()> = Graph::new();
+            lonely.insert_node(0, ());
+            lonely.add_edge(1, 1, ());
+            assert_eq!(chromatic_number(&lonely), 1);
+            assert_eq!(chromatic_number(&Graph::<u32>::new()), 0);
+        }
+    }
+}
+
+pub mod clique {
+    use super::graph::Graph;
+    use super::*;
+
+    struct Frame {
+        depth: usize,
+        candidates: Vec<usize>,
+        excluded: Vec<usize>,
+        branches: Vec<usize>,
+    }
+
+    // Bron-Kerbosch with Tomita pivoting, started once per node in degeneracy
+    // order. The recursion lives on an explicit stack so cliques come out one
+    // at a time.
+    pub struct MaximalCliques<'a, Nid> {
+        node_ids: Vec<&'a Nid>,
+        // Sorted neighbour lists without self-loops.
+        adjacent: Vec<Vec<usize>>,
+        order: Vec<usize>,
+        position: Vec<usize>,
+        next_root: usize,
+        clique: Vec<usize>,
+        stack: Vec<Frame>,
+    }
+
+    impl<Nid> MaximalCliques<'_, Nid> {
+        fn frame(&self, candidates: Vec<usize>, excluded: Vec<usize>) -> Frame {
+            let pivot = candidates
+                .iter()
+                .chain(excluded.iter())
+                .max_by_key(|node| {
+                    candidates
+                        .iter()
+                        .filter(|other| self.adjacent[**node].binary_search(other).is_ok())
+                        .count()
+                })
+                .copied();
+            let branches = match pivot {
+                Some(pivot) => candidates
+                    .iter()
+                    .filter(|node| self.adjacent[pivot].binary_search(node).is_err())
+                    .copied()
+                    .collect(),
+                None => vec![],
+            };
+            Frame {
+                depth: self.clique.len(),
+                candidates,
+                excluded,
+                branches,
+            }
+        }
+
+        // Extends the current clique by `candidates`, or reports that it is
+        // already maximal.
+        fn descend(&mut self, candidates: Vec<usize>, excluded: Vec<usize>) -> bool {
+            if candidates.is_empty() {
+                return excluded.is_empty();
+            }
+            let frame = self.frame(candidates, excluded);
+            self.stack.push(frame);
+            false
+        }
+    }
+
+    impl<Nid> Iterator for MaximalCliques<'_, Nid>
+    where
+        Nid: Clone,
+    {
+        type Item = Vec<Nid>;
+
+        fn next(&mut self) -> Option<Vec<Nid>> {
+            loop {
+                let maximal = match self.stack.last_mut() {
+                    Some(frame) => {
+                        let Some(node) = frame.branches.pop() else {
+                            self.stack.pop();
+                            continue;
+                        };
+                        let adjacent = &self.adjacent[node];
+                        let candidates: Vec<usize> = frame
+                            .candidates
+                            .iter()
+                            .filter(|other| adjacent.binary_search(other).is_ok())
+                            .copied()
+                            .collect();
+                        let excluded: Vec<usize> = frame
+                            .excluded
+                            .iter()
+                            .filter(|other| adjacent.binary_search(other).is_ok())
+                            .copied()
+                            .collect();
+                        frame.candidates.retain(|other| *other != node);
+                        frame.excluded.push(node);
+                        self.clique.truncate(frame.depth);
+                        self.clique.push(node);
+                        self.descend(candidates, excluded)
+                    }
+                    None => {
+                        let node = *self.order.get(self.next_root)?;
+                        self.next_root += 1;
+                        let (later, earlier): (Vec<usize>, Vec<usize>) = self.adjacent[node]
+                            .iter()
+                            .partition(|other| self.position[**other] > self.position[node]);
+                        self.clique.clear();
+                        self.clique.push(node);
+                        self.descend(later, earlier)
+                    }
+                };
+                if maximal {
+                    return Some(
+                        self.clique
+                            .iter()
+                            .map(|node| self.node_ids[*node].clone())
+                            .collect(),
+                    );
+                }
+            }
+        }
+    }
+
+    // Repeatedly removes a node of smallest remaining degree.
+    fn degeneracy_order(adjacent: &[Vec<usize>]) -> Vec<usize> {
+        let count = adjacent.len();
+        let mut degree: Vec<usize> = adjacent.iter().map(|list| list.len()).collect();
+        let mut buckets: Vec<Vec<usize>> = vec![vec![]; count];
+        for (node, degree) in degree.iter().enumerate() {
+            buckets[*degree].push(node);
+        }
+        let mut removed = vec![false; count];
+        let mut order = Vec::with_capacity(count);
+        let mut lowest = 0;
+        while order.len() < count {
+            lowest = lowest.min(count - 1);
+            while buckets[lowest].is_empty() {
+                lowest += 1;
+            }
+            let node = buckets[lowest].pop().unwrap();
+            if removed[node] || degree[node] != lowest {
+                continue;
+            }
+            removed[node] = true;
+            order.push(node);
+            for other in adjacent[node].iter() {
+                if !removed[*other] {
+                    degree[*other] -= 1;
+                    buckets[degree[*other]].push(*other);
+                    lowest = lowest.min(degree[*other]);
+                }
+            }
+        }
+        order
+    }
+
+    // Edge directions are ignored and self-loops do not count as adjacency.
+    pub fn maximal_cliques<Nid, N, E>(graph: &Graph<Nid, N, E>) -> MaximalCliques<'_, Nid>
+    where
+        Nid: Hash + Eq,
+    {
+        let (node_ids, index) = graph.index_nodes();
+        let mut adjacent = graph.neighbour_lists(&index);
+        for (node, list) in adjacent.iter_mut().enumerate() {
+            list.retain(|other| *other != node);
+            list.sort_unstable();
+            list.dedup();
+        }
+        let order = degeneracy_order(&adjacent);
+        let mut position = vec![0; order.len()];
+        for (rank, node) in order.iter().enumerate() {
+            position[*node] = rank;
+        }
+        MaximalCliques {
+            node_ids,
+            adjacent,
+            order,
+            position,
+            next_root: 0,
+            clique: vec![],
+            stack: vec![],
+        }
+    }
+
+    pub fn maximum_clique<Nid, N, E>(graph: &Graph<Nid, N, E>) -> Vec<Nid>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        maximal_cliques(graph)
+            .max_by_key(|clique| clique.len())
+            .unwrap_or_default()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::super::test_support::{lcg, undirected};
+        use super::*;
+
+        fn sorted(cliques: impl Iterator<Item = Vec<u32>>) -> Vec<Vec<u32>> {
+            let mut cliques: Vec<Vec<u32>> = cliques
+                .map(|mut clique| {
+                    clique.sort();
+                    clique
+                })
+                .collect();
+            cliques.sort();
+            cliques
+        }
+
+        #[test]
+        fn maximal_cliques_cover_every_node() {
+            let mut graph = undirected(&[
+                (0, 1),
+                (0, 2),
+                (0, 3),
+                (1, 2),
+                (1, 3),
+                (2, 3),
+                (3, 4),
+                (4, 5),
+                (5, 3),
+                (5, 6),
+                (6, 5),
+                (8, 8),
+            ]);
+            graph.insert_node(7, ());
+            assert_eq!(
+                sorted(maximal_cliques(&graph)),
+                vec![
+                    vec![0, 1, 2, 3],
+                    vec![3, 4, 5],
+                    vec![5, 6],
+                    vec![7],
+                    vec![8]
+                ]
+            );
+            let mut largest = maximum_clique(&graph);
+            largest.sort();
+            assert_eq!(largest, vec![0, 1, 2, 3]);
+
+            assert_eq!(maximal_cliques(&Graph::<u32>::new()).count(), 0);
+            assert!(maximum_clique(&Graph::<u32>::new()).is_empty());
+        }
+
+        #[test]
+        fn complete_tripartite_graphs_have_every_cross_triple() {
+            let mut edges = vec![];
+            for a in 0..9 {
+                for b in a + 1..9 {
+                    if a / 3 != b / 3 {
+                        edges.push((a, b));
+                    }
+                }
+            }
+            let graph = undirected(&edges);
+            let cliques = sorted(maximal_cliques(&graph));
+            assert_eq!(cliques.len(), 27);
+            assert!(cliques.iter().all(|clique| clique.len() == 3));
+        }
+
+        #[test]
+        fn maximal_cliques_agree_with_brute_force() {
+            let mut next = lcg(11);
+            for _ in 0..100 {
+                let mut edges = vec![];
+                for a in 0..8 {
+                    for b in a + 1..8 {
+                        if next().is_multiple_of(2) {
+                            edges.push((a, b));
+                        }
+                    }
+                }
+                let mut graph = undirected(&edges);
+                for node_id in 0..8 {
+                    graph.insert_node(node_id, ());
+                }
+                let adjacent = |a: u32, b: u32| edges.contains(&(a.min(b), a.max(b)));
+                let is_clique = |members: &[u32]| {
+                    members
+                        .iter()
+                        .all(|a| members.iter().all(|b| a == b || adjacent(*a, *b)))
+                };
+                let mut expected = vec![];
+                for subset in 1u32..256 {
+                    let members: Vec<u32> =
+                        (0..8).filter(|node| subset & (1 << node) != 0).collect();
+                    let maximal = (0..8).all(|other| {
+                        members.contains(&other)
+                            || !members.iter().all(|member| adjacent(*member, other))
+                    });
+                    if is_clique(&members) && maximal {
+                        expected.push(members);
+                    }
+                }
+                expected.sort();
+                assert_eq!(sorted(maximal_cliques(&graph)), expected);
+                let largest = expected.iter().map(|clique| clique.len()).max().unwrap();
+                assert_eq!(maximum_clique(&graph).len(), largest);
+            }
+        }
+    }
+}
+
+pub mod isomorphism {
+    use super::graph::Graph;
+    use super::*;
+
+    const UNMAPPED: usize = usize::MAX;
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Mode {
+        Isomorphism,
+        // Pattern edges must be present and target edges between mapped nodes
+        // must come from the pattern.
+        Induced,
+        // Pattern edges must be present, the target may have more.
+        Monomorphism,
+    }
+
+    struct Side<'a, Nid, N, E> {
+        node_ids: Vec<&'a Nid>,
+        payloads: Vec<Option<&'a N>>,
+        successors: Vec<Vec<usize>>,
+        predecessors: Vec<Vec<usize>>,
+        edges: HashMap<(usize, usize), Vec<&'a E>>,
+        // Mapped partner of every node, and the depth at which it joined the
+        // out and in terminal sets (zero while outside them).
+        core: Vec<usize>,
+        out_depth: Vec<usize>,
+        in_depth: Vec<usize>,
+    }
+
+    impl<'a, Nid, N, E> Side<'a, Nid, N, E>
+    where
+        Nid: Hash + Eq,
+    {
+        fn new(graph: &'a Graph<Nid, N, E>) -> Self {
+            let (node_ids, index) = graph.index_nodes();
+            let count = node_ids.len();
+            let payloads = node_ids
+                .iter()
+                .map(|node_id| graph.get_node(node_id))
+                .collect();
+            let mut successors = vec![vec![]; count];
+            let mut predecessors = vec![vec![]; count];
+            let mut edges: HashMap<(usize, usize), Vec<&E>> = HashMap::new();
+            for (from, list) in graph.iter_edges() {
+                for (to, edge) in list.iter() {
+                    let (from, to) = (index[from], index[to]);
+                    let parallel = edges.entry((from, to)).or_default();
+                    if parallel.is_empty() && from != to {
+                        successors[from].push(to);
+                        predecessors[to].push(from);
+                    }
+                    parallel.push(edge);
+                }
+            }
+            Side {
+                node_ids,
+                payloads,
+                successors,
+                predecessors,
+                edges,
+                core: vec![UNMAPPED; count],
+                out_depth: vec![0; count],
+                in_depth: vec![0; count],
+            }
+        }
+
+        fn len(&self) -> usize {
+            self.node_ids.len()
+        }
+
+        fn edge_total(&self) -> usize {
+            self.edges.values().map(Vec::len).sum()
+        }
+
+        fn edges_between(&self, from: usize, to: usize) -> &[&'a E] {
+            self.edges.get(&(from, to)).map_or(&[], |edges| edges)
+        }
+
+        fn map(&mut self, node: usize, partner: usize, depth: usize) {
+            self.core[node] = partner;
+            for (depths, neighbours) in [
+                (&mut self.out_depth, &self.successors[node]),
+                (&mut self.in_depth, &self.predecessors[node]),
+            ] {
+                for other in std::iter::once(&node).chain(neighbours.iter()) {
+                    if depths[*other] == 0 {
+                        depths[*other] = depth;
+                    }
+                }
+            }
+        }
+
+        fn unmap(&mut self, node: usize, depth: usize) {
+            self.core[node] = UNMAPPED;
+            for (depths, neighbours) in [
+                (&mut self.out_depth, &self.successors[node]),
+                (&mut self.in_depth, &self.predecessors[node]),
+            ] {
+                for other in std::iter::once(&node).chain(neighbours.iter()) {
+                    if depths[*other] == depth {
+                        depths[*other] = 0;
+                    }
+                }
+            }
+        }
+
+        fn unmapped(&self) -> impl Iterator<Item = usize> + '_ {
+            (0..self.len()).filter(|node| self.core[*node] == UNMAPPED)
+        }
+
+        // Unmapped neighbours in the out terminal set, the in terminal set and
+        // outside both.
+        fn lookahead(&self, neighbours: &[usize]) -> (usize, usize, usize) {
+            let mut counts = (0, 0, 0);
+            for other in neighbours
+                .iter()
+                .filter(|other| self.core[**other] == UNMAPPED)
+            {
+                if self.out_depth[*other] > 0 {
+                    counts.0 += 1;
+                }
+                if self.in_depth[*other] > 0 {
+                    counts.1 += 1;
+                }
+                if self.out_depth[*other] == 0 && self.in_depth[*other] == 0 {
+                    counts.2 += 1;
+                }
+            }
+            counts
+        }
+    }
+
+    // Pairs every pattern edge with its own target edge, parallel edges
+    // included. Spare target edges are only allowed when `exact` is unset.
+    fn edges_compatible<E1, E2, EM>(
+        pattern: &[&E1],
+        target: &[&E2],
+        exact: bool,
+        edge_match: &mut EM,
+    ) -> bool
+    where
+        EM: FnMut(&E1, &E2) -> bool,
+    {
+        if pattern.len() > target.len() || (exact && pattern.len() != target.len()) {
+            return false;
+        }
+        let allowed: Vec<Vec<usize>> = pattern
+            .iter()
+            .map(|edge| {
+                (0..target.len())
+                    .filter(|other| edge_match(edge, target[*other]))
+                    .collect()
+            })
+            .collect();
+        let mut owner = vec![UNMAPPED; target.len()];
+        (0..pattern.len()).all(|edge| {
+            let mut seen = vec![false; target.len()];
+            claim_edge(edge, &allowed, &mut owner, &mut seen)
+        })
+    }
+
+    fn claim_edge(
+        edge: usize,
+        allowed: &[Vec<usize>],
+        owner: &mut [usize],
+        seen: &mut [bool],
+    ) -> bool {
+        for other in allowed[edge].iter() {
+            if seen[*other] {
+                continue;
+            }
+            seen[*other] = true;
+            if owner[*other] == UNMAPPED || claim_edge(owner[*other], allowed, owner, seen) {
+                owner[*other] = edge;
+                return true;
+            }
+        }
+        false
+    }
+
+    struct Frame {
+        node: usize,
+        candidates: Vec<usize>,
+        next: usize,
+        current: Option<usize>,
+    }
+
+    // VF2 search mapping every pattern node onto a distinct target node, driven
+    // by an explicit stack so mappings can be produced one by one.
+    struct Vf2<'a, Nid1, N1, E1, Nid2, N2, E2, NM, EM> {
+        pattern: Side<'a, Nid1, N1, E1>,
+        target: Side<'a, Nid2, N2, E2>,
+        mode: Mode,
+        // Without a node matcher payloads are not compared at all.
+        node_match: Option<NM>,
+        edge_match: EM,
+        stack: Vec<Frame>,
+        started: bool,
+    }
+
+    impl<'a, Nid1, N1, E1, Nid2, N2, E2, NM, EM> Vf2<'a, Nid1, N1, E1, Nid2, N2, E2, NM, EM>
+    where
+        Nid1: Hash + Eq,
+        Nid2: Hash + Eq,
+        NM: FnMut(&N1, &N2) -> bool,
+        EM: FnMut(&E1, &E2) -> bool,
+    {
+        fn new(
+            pattern: &'a Graph<Nid1, N1, E1>,
+            target: &'a Graph<Nid2, N2, E2>,
+            mode: Mode,
+            node_match: Option<NM>,
+            edge_match: EM,
+        ) -> Self {
+            Vf2 {
+                pattern: Side::new(pattern),
+                target: Side::new(target),
+                mode,
+                node_match,
+                edge_match,
+                stack: vec![],
+                started: false,
+            }
+        }
+
+        // Prefers a pattern node hanging off the mapped part, whose partner then
+        // has to be a matching neighbour of its mapped neighbour's partner.
+        fn frame(&self) -> Frame {
+            let (pattern, target) = (&self.pattern, &self.target);
+            let anchored = pattern.unmapped().find_map(|node| {
+                let mapped = |other: &&usize| pattern.core[**other] != UNMAPPED;
+                let from_predecessor = pattern.predecessors[node]
+                    .iter()
+                    .find(mapped)
+                    .map(|other| &target.successors[pattern.core[*other]]);
+                from_predecessor
+                    .or_else(|| {
+                        pattern.successors[node]
+                            .iter()
+                            .find(mapped)
+                            .map(|other| &target.predecessors[pattern.core[*other]])
+                    })
+                    .map(|neighbours| (node, neighbours))
+            });
+            let (node, candidates) = match anchored {
+                Some((node, neighbours)) => (
+                    node,
+                    neighbours
+                        .iter()
+                        .filter(|other| target.core[**other] == UNMAPPED)
+                        .copied()
+                        .collect(),
+                ),
+                None => (
+                    pattern.unmapped().next().unwrap(),
+                    target.unmapped().collect(),
+                ),
+            };
+            Frame {
+                node,
+                candidates,
+                next: 0,
+                current: None,
+            }
+        }
+
+        fn feasible(&mut self, node: usize, candidate: usize) -> bool {
+            let (pattern, target) = (&self.pattern, &self.target);
+            // Nodes that only appear as edge endpoints carry no payload and are
+            // never passed to the node matcher. Between whole graphs they only
+            // match each other, a payload-free pattern node matches any node.
+            if let Some(node_match) = self.node_match.as_mut() {
+                let payloads_match = match (pattern.payloads[node], target.payloads[candidate]) {
+                    (Some(first), Some(second)) => node_match(first, second),
+                    (None, None) => true,
+                    (None, Some(_)) => self.mode != Mode::Isomorphism,
+                    (Some(_), None) => false,
+                };
+                if !payloads_match {
+                    return false;
+                }
+            }
+
+            let mut pairs = vec![(node, node, candidate, candidate)];
+            for other in pattern.predecessors[node].iter() {
+                if pattern.core[*other] != UNMAPPED {
+                    pairs.push((*other, node, pattern.core[*other], candidate));
+                }
+            }
+            for other in pattern.successors[node].iter() {
+                if pattern.core[*other] != UNMAPPED {
+                    pairs.push((node, *other, candidate, pattern.core[*other]));
+                }
+            }
+            let exact = self.mode != Mode::Monomorphism;
+            if exact {
+                for other in target.predecessors[candidate].iter() {
+                    if target.core[*other] != UNMAPPED {
+                        pairs.push((target.core[*other], node, *other, candidate));
+                    }
+                }
+                for other in target.successors[candidate].iter() {
+                    if target.core[*other] != UNMAPPED {
+                        pairs.push((node, target.core[*other], candidate, *other));
+                    }
+                }
+            }
+            for (from, to, target_from, target_to) in pairs {
+                let edges = pattern.edges_between(from, to);
+                let target_edges = target.edges_between(target_from, target_to);
+                if !edges_compatible(edges, target_edges, exact, &mut self.edge_match) {
+                    return false;
+                }
+            }
+
+            let fits = |first: (usize, usize, usize), second: (usize, usize, usize)| match self.mode
+            {
+                Mode::Isomorphism => first == second,
+                Mode::Induced => first.0 <= second.0 && first.1 <= second.1 && first.2 <= second.2,
+                // Neighbours outside the terminal sets may land on target nodes
+                // inside them, so only the terminal counts are bounded.
+                Mode::Monomorphism => first.0 <= second.0 && first.1 <= second.1,
+            };
+            fits(
+                pattern.lookahead(&pattern.predecessors[node]),
+                target.lookahead(&target.predecessors[candidate]),
+            ) && fits(
+                pattern.lookahead(&pattern.successors[node]),
+                target.lookahead(&target.successors[candidate]),
+            )
+        }
+
+        fn next_mapping(&mut self) -> Option<Vec<usize>> {
+            if !self.started {
+                self.started = true;
+                if self.pattern.len() == 0 {
+                    return Some(vec![]);
+                }
+                let frame = self.frame();
+                self.stack.push(frame);
+            }
+            loop {
+                let depth = self.stack.len();
+                let frame = self.stack.last_mut()?;
+                let node = frame.node;
+                if let Some(candidate) = frame.current.take() {
+                    self.pattern.unmap(node, depth);
+                    self.target.unmap(candidate, depth);
+                }
+                let mut chosen = None;
+                while let Some(candidate) = {
+                    let frame = &mut self.stack[depth - 1];
+                    frame.next += 1;
+                    frame.candidates.get(frame.next - 1).copied()
+                } {
+                    if self.feasible(node, candidate) {
+                        chosen = Some(candidate);
+                        break;
+                    }
+                }
+                let Some(candidate) = chosen else {
+                    self.stack.pop();
+                    continue;
+                };
+                self.stack[depth - 1].current = Some(candidate);
+                self.pattern.map(node, candidate, depth);
+                self.target.map(candidate, node, depth);
+                if depth == self.pattern.len() {
+                    return Some(self.pattern.core.clone());
+                }
+                let frame = self.frame();
+                self.stack.push(frame);
+            }
+        }
+
+        fn to_mapping(&self, core: Vec<usize>) -> HashMap<Nid1, Nid2>
+        where
+            Nid1: Clone,
+            Nid2: Clone,
+        {
+            core.into_iter()
+                .enumerate()
+                .map(|(node, partner)| {
+                    (
+                        self.pattern.node_ids[node].clone(),
+                        self.target.node_ids[partner].clone(),
+                    )
+                })
+                .collect()
+        }
+    }
+
+    pub fn is_isomorphic<Nid1, N1, E1, Nid2, N2, E2>(
+        first: &Graph<Nid1, N1, E1>,
+        second: &Graph<Nid2, N2, E2>,
+    ) -> bool
+    where
+        Nid1: Hash + Eq + Clone,
+        Nid2: Hash + Eq + Clone,
+    {
+        isomorphism_mapping(first, second).is_some()
+    }
+
+    pub fn isomorphism_mapping<Nid1, N1, E1, Nid2, N2, E2>(
+        first: &Graph<Nid1, N1, E1>,
+        second: &Graph<Nid2, N2, E2>,
+    ) -> Option<HashMap<Nid1, Nid2>>
+    where
+        Nid1: Hash + Eq + Clone,
+        Nid2: Hash + Eq + Clone,
+    {
+        find_isomorphism::<_, _, _, _, _, _, fn(&N1, &N2) -> bool, _>(
+            first,
+            second,
+            None,
+            |_, _| true,
+        )
+    }
+
+    pub fn is_isomorphic_matching<Nid1, N1, E1, Nid2, N2, E2, NM, EM>(
+        first: &Graph<Nid1, N1, E1>,
+        second: &Graph<Nid2, N2, E2>,
+        node_match: NM,
+        edge_match: EM,
+    ) -> bool
+    where
+        Nid1: Hash + Eq + Clone,
+        Nid2: Hash + Eq + Clone,
+        NM: FnMut(&N1, &N2) -> bool,
+        EM: FnMut(&E1, &E2) -> bool,
+    {
+        isomorphism_mapping_matching(first, second, node_match, edge_match).is_some()
+    }
+
+    pub fn isomorphism_mapping_matching<Nid1, N1, E1, Nid2, N2, E2, NM, EM>(
+        first: &Graph<Nid1, N1, E1>,
+        second: &Graph<Nid2, N2, E2>,
+        node_match: NM,
+        edge_match: EM,
+    ) -> Option<HashMap<Nid1, Nid2>>
+    where
+        Nid1: Hash + Eq + Clone,
+        Nid2: Hash + Eq + Clone,
+        NM: FnMut(&N1, &N2) -> bool,
+        EM: FnMut(&E1, &E2) -> bool,
+    {
+        find_isomorphism(first, second, Some(node_match), edge_match)
+    }
+
+    fn find_isomorphism<Nid1, N1, E1, Nid2, N2, E2, NM, EM>(
+        first: &Graph<Nid1, N1, E1>,
+        second: &Graph<Nid2, N2, E2>,
+        node_match: Option<NM>,
+        edge_match: EM,
+    ) -> Option<HashMap<Nid1, Nid2>>
+    where
+        Nid1: Hash + Eq + Clone,
+        Nid2: Hash + Eq + Clone,
+        NM: FnMut(&N1, &N2) -> bool,
+        EM: FnMut(&E1, &E2) -> bool,
+    {
+        let mut search = Vf2::new(first, second, Mode::Isomorphism, node_match, edge_match);
+        if search.pattern.len() != search.target.len()
+            || search.pattern.edge_total() != search.target.edge_total()
+        {
+            return None;
+        }
+        let core = search.next_mapping()?;
+        Some(search.to_mapping(core))
+    }
+
+    pub struct SubgraphEmbeddings<'a, Nid1, N1, E1, Nid2, N2, E2, NM, EM> {
+        search: Vf2<'a, Nid1, N1, E1, Nid2, N2, E2, NM, EM>,
+    }
+
+    impl<Nid1, N1, E1, Nid2, N2, E2, NM, EM> Iterator
+        for SubgraphEmbeddings<'_, Nid1, N1, E1, Nid2, N2, E2, NM, EM>
+    where
+        Nid1: Hash + Eq + Clone,
+        Nid2: Hash + Eq + Clone,
+        NM: FnMut(&N1, &N2) -> bool,
+        EM: FnMut(&E1, &E2) -> bool,
+    {
+        type Item = HashMap<Nid1, Nid2>;
+
+        fn next(&mut self) -> Option<HashMap<Nid1, Nid2>> {
+            let core = self.search.next_mapping()?;
+            Some(self.search.to_mapping(core))
+        }
+    }
+
+    // Yields every injective mapping of pattern nodes onto target nodes that
+    // carries pattern edges onto target edges. With `induced` set, target edges
+    // between mapped nodes must also exist in the pattern. Symmetric patterns
+    // show up once per automorphism. Pattern nodes without a payload match any
+    // target node.
+    pub fn subgraph_embeddings<'a, Nid1, N1, E1, Nid2, N2, E2, NM, EM>(
+        pattern: &'a Graph<Nid1, N1, E1>,
+        target: &'a Graph<Nid2, N2, E2>,
+        induced: bool,
+        node_match: NM,
+        edge_match: EM,
+    ) -> SubgraphEmbeddings<'a, Nid1, N1, E1, Nid2, N2, E2, NM, EM>
+    where
+        Nid1: Hash + Eq + Clone,
+        Nid2: Hash + Eq + Clone,
+        NM: FnMut(&N1, &N2) -> bool,
+        EM: FnMut(&E1, &E2) -> bool,
+    {
+        let mode = if induced {
+            Mode::Induced
+        } else {
+            Mode::Monomorphism
+        };
+        SubgraphEmbeddings {
+            search: Vf2::new(pattern, target, mode, Some(node_match), edge_match),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::super::test_support::{directed, undirected};
+        use super::*;
+
+        fn petersen(shift: u32) -> Graph<u32, (), ()> {
+            let mut graph = Graph::new();
+            for i in 0..5 {
+                let node = |index: u32| (index * 7 + shift) % 10;
+                for (from, to) in [(i, (i + 1) % 5), (i, i + 5), (i + 5, (i + 2) % 5 + 5)] {
+                    graph.push_undirected_edge(node(from), node(to), ());
+                }
+            }
+            graph
+        }
+
+        fn assert_edges_carried(
+            first: &Graph<u32, (), ()>,
+            second: &Graph<u32, (), ()>,
+            mapping: &HashMap<u32, u32>,
+        ) {
+            assert_eq!(mapping.len(), first.node_ids().len());
+            let images: HashSet<&u32> = mapping.values().collect();
+            assert_eq!(images.len(), mapping.len());
+            for (from, edges) in first.iter_edges() {
+                for (to, _) in edges.iter() {
+                    assert_eq!(
+                        first.edge_count(from, to),
+                        second.edge_count(&mapping[from], &mapping[to])
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn relabelled_graphs_are_isomorphic() {
+            let (first, second) = (petersen(0), petersen(3));
+            let mapping = isomorphism_mapping(&first, &second).unwrap();
+            assert_edges_carried(&first, &second, &mapping);
+
+            let cycle = directed(&[(0, 1), (1, 2), (2, 0)]);
+            let rotated = directed(&[(7, 9), (9, 8), (8, 7)]);
+            assert!(is_isomorphic(&cycle, &rotated));
+            assert!(is_isomorphic(&Graph::<u32>::new(), &Graph::<u32>::new()));
+        }
+
+        #[test]
+        fn equal_degrees_are_not_enough() {
+            let hexagon = directed(&[(0, 1), (1, 2), (2, 3), (3, 4), (4, 5), (5, 0)]);
+            let triangles = directed(&[(0, 1), (1, 2), (2, 0), (3, 4), (4, 5), (5, 3)]);
+            assert!(!is_isomorphic(&hexagon, &triangles));
+
+            let out_star = directed(&[(0, 1), (0, 2)]);
+            let in_star = directed(&[(1, 0), (2, 0)]);
+            assert!(!is_isomorphic(&out_star, &in_star));
+
+            let single = directed(&[(0, 1), (1, 0)]);
+            let doubled = directed(&[(0, 1), (0, 1)]);
+            assert!(!is_isomorphic(&single, &doubled));
+            assert!(!is_isomorphic(&single, &directed(&[(0, 1), (1, 2)])));
+        }
+
+        #[test]
+        fn payloads_restrict_the_mapping() {
+            let mut first: Graph<u32, char, u32> = Graph::new();
+            first.insert_node(0, 'a');
+            first.insert_node(1, 'b');
+            first.insert_node(2, 'b');
+            first.add_edge(0, 1, 5);
+            first.add_edge(0, 2, 6);
+            let mut second: Graph<u32, char, u32> = Graph::new();
+            second.insert_node(10, 'b');
+            second.insert_node(11, 'a');
+            second.insert_node(12, 'b');
+            second.add_edge(11, 10, 6);
+            second.add_edge(11, 12, 5);
+
+            let mapping =
+                isomorphism_mapping_matching(&first, &second, |a, b| a == b, |a, b| a == b)
+                    .unwrap();
+            assert_eq!(mapping, HashMap::from([(0, 11), (1, 12), (2, 10)]));
+
+            second.insert_node(11, 'b');
+            assert!(!is_isomorphic_matching(
+                &first,
+                &second,
+                |a, b| a == b,
+                |_, _| true
+            ));
+            assert!(is_isomorphic_matching(
+                &first,
+                &second,
+                |_, _| true,
+                |a, b| a == b
+            ));
+        }
+
+        #[test]
+        fn payload_free_pattern_nodes_match_nodes_with_payloads() {
+            let mut pattern: Graph<u32, &str, ()> = Graph::new();
+            pattern.add_edge(0, 1, ());
+            let mut target: Graph<u32, &str, ()> = Graph::new();
+            target.insert_node(10, "account");
+            target.insert_node(11, "account");
+            target.add_edge(10, 11, ());
+            let embeddings: Vec<_> =
+                subgraph_embeddings(&pattern, &target, false, |_, _| true, |_, _| true).collect();
+            assert_eq!(embeddings, vec![HashMap::from([(0, 10), (1, 11)])]);
+
+            pattern.insert_node(0, "merchant");
+            let rejected = subgraph_embeddings(&pattern, &target, true, |a, b| a == b, |_, _| true);
+            assert_eq!(rejected.count(), 0);
+            assert!(is_isomorphic(&pattern, &target));
+
+            // Between whole graphs a payload-free node only matches another one.
+            pattern.insert_node(0, "account");
+            let embeddings =
+                subgraph_embeddings(&pattern, &target, true, |a, b| a == b, |_, _| true);
+            assert_eq!(embeddings.count(), 1);
+            assert!(
+                isomorphism_mapping_matching(&pattern, &target, |_, _| true, |_, _| true).is_none()
+            );
+            let mut untagged: Graph<u32, &str, ()> = Graph::new();
+            untagged.insert_node(10, "account");
+            untagged.add_edge(10, 11, ());
+            assert_eq!(
+                isomorphism_mapping_matching(&pattern, &untagged, |a, b| a == b, |_, _| true),
+                Some(HashMap::from([(0, 10), (1, 11)]))
+            );
+        }
+
+        fn embedding_count(
+            pattern: &Graph<u32, (), ()>,
+            target: &Graph<u32, (), ()>,
+            induced: bool,
+        ) -> usize {
+            subgraph_embeddings(pattern, target, induced, |_, _| true, |_, _| true)
+                .inspect(|mapping| {
+                    for (from, edges) in pattern.iter_edges() {
+                        for (to, _) in edges.iter() {
+                            assert!(target.get_edge(&mapping[from], &mapping[to]).is_some());
+                        }
+                    }
+                })
+                .count()
+        }
+
+        #[test]
+        fn induced_embeddings_reject_extra_target_edges() {
+            let path = undirected(&[(0, 1), (1, 2)]);
+            let square = undirected(&[(0, 1), (1, 2), (2, 3), (3, 0)]);
+            let complete = undirected(&[(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)]);
+            assert_eq!(embedding_count(&path, &square, false), 8);
+            assert_eq!(embedding_count(&path, &square, true), 8);
+            assert_eq!(embedding_count(&path, &complete, false), 24);
+            assert_eq!(embedding_count(&path, &complete, true), 0);
+
+            let triangle = undirected(&[(0, 1), (1, 2), (2, 0)]);
+            assert_eq!(embedding_count(&triangle, &complete, true), 24);
+            assert_eq!(embedding_count(&triangle, &square, false), 0);
+            assert_eq!(embedding_count(&complete, &triangle, false), 0);
+        }
+
+        #[test]
+        fn embeddings_respect_direction_and_payloads() {
+            let mut arc: Graph<u32, (), u32> = Graph::new();
+            arc.add_edge(0, 1, 1);
+            let mut cycle: Graph<u32, (), u32> = Graph::new();
+            cycle.add_edge(10, 11, 1);
+            cycle.add_edge(11, 12, 2);
+            cycle.add_edge(12, 10, 1);
+            let all: Vec<_> =
+                subgraph_embeddings(&arc, &cycle, false, |_, _| true, |_, _| true).collect();
+            assert_eq!(all.len(), 3);
+
+            let mut heavy: Vec<HashMap<u32, u32>> =
+                subgraph_embeddings(&arc, &cycle, true, |_, _| true, |a, b| a == b).collect();
+            heavy.sort_by_key(|mapping| mapping[&0]);
+            assert_eq!(
+                heavy,
+                vec![
+                    HashMap::from([(0, 10), (1, 11)]),
+                    HashMap::from([(0, 12), (1, 10)]),
+                ]
+            );
+
+            let mut backwards: Graph<u32, (), u32> = Graph::new();
+            backwards.add_edge(0, 1, 1);
+            backwards.add_edge(1, 0, 1);
+            assert_eq!(
+                subgraph_embeddings(&backwards, &cycle, false, |_, _| true, |_, _| true).count(),
+                0
+            );
+        }
+    }
+}
+
+pub mod centrality {
+    use super::graph::Graph;
+    use super::shortest_path::{Measure, MinScored};
+    use super::*;
+
+    // Where the score of a node without outgoing weight goes on every step.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Dangling {
+        Uniform,
+        Personalization,
+        SelfLoop,
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct PageRankOptions {
+        pub damping: f64,
+        // Convergence is reached once the summed change of all scores drops
+        // below `tolerance` times the node count.
+        pub tolerance: f64,
+        pub max_iterations: usize,
+        pub dangling: Dangling,
+    }
+
+    impl Default for PageRankOptions {
+        fn default() -> Self {
+            PageRankOptions {
+                damping: 0.85,
+                tolerance: 1e-6,
+                max_iterations: 100,
+                dangling: Dangling::Uniform,
+            }
+        }
+    }
+
+    pub fn page_rank<Nid, N, E>(
+        graph: &Graph<Nid, N, E>,
+        options: &PageRankOptions,
+    ) -> Result<HashMap<Nid, f64>, &'static str>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        rank(graph, options, |_| 1.0, None)
+    }
+
+    pub fn weighted_page_rank<Nid, N, E, F>(
+        graph: &Graph<Nid, N, E>,
+        options: &PageRankOptions,
+        weight: F,
+    ) -> Result<HashMap<Nid, f64>, &'static str>
+    where
+        Nid: Hash + Eq + Clone,
+        F: FnMut(&E) -> f64,
+    {
+        rank(graph, options, weight, None)
+    }
+
+    // Teleports land on nodes in proportion to `personalization`; nodes missing
+    // from it are never teleported to.
+    pub fn personalized_page_rank<Nid, N, E, F>(
+        graph: &Graph<Nid, N, E>,
+        options: &PageRankOptions,
+        weight: F,
+        personalization: &HashMap<Nid, f64>,
+    ) -> Result<HashMap<Nid, f64>, &'static str>
+    where
+        Nid: Hash + Eq + Clone,
+        F: FnMut(&E) -> f64,
+    {
+        rank(graph, options, weight, Some(personalization))
+    }
+
+    fn rank<Nid, N, E, F>(
+        graph: &Graph<Nid, N, E>,
+        options: &PageRankOptions,
+        mut weight: F,
+        personalization: Option<&HashMap<Nid, f64>>,
+    ) -> Result<HashMap<Nid, f64>, &'static str>
+    where
+        Nid: Hash + Eq + Clone,
+        F: FnMut(&E) -> f64,
+    {
+        if !(0.0..=1.0).contains(&options.damping) {
+            return Err("Damping has to lie between 0 and 1.");
+        }
+        let (node_ids, index) = graph.index_nodes();
+        let count = node_ids.len();
+        if count == 0 {
+            return Ok(HashMap::new());
+        }
+
+        let mut teleport = vec![1.0 / count as f64; count];
+        if let Some(personalization) = personalization {
+            teleport = node_ids
+                .iter()
+                .map(|node_id| personalization.get(*node_id).copied().unwrap_or(0.0))
+                .collect();
+            if teleport.iter().any(|share| share.is_nan() || *share < 0.0) {
+                return Err("Personalization values have to be non-negative.");
+            }
+            let total: f64 = teleport.iter().sum();
+            if total <= 0.0 {
+                return Err("Personalization has to favour at least one node.");
+            }
+            teleport.iter_mut().for_each(|share| *share /= total);
+        }
+
+        // Parallel edges add up their weights.
+        let mut links: Vec<HashMap<usize, f64>> = vec![HashMap::new(); count];
+        for (from, edges) in graph.iter_edges() {
+            for (to, edge) in edges.iter() {
+                let value = weight(edge);
+                if value.is_nan() || value < 0.0 {
+                    return Err("Edge weights have to be non-negative.");
+                }
+                *links[index[from]].entry(index[to]).or_insert(0.0) += value;
+            }
+        }
+        let out_weight: Vec<f64> = links.iter().map(|links| links.values().sum()).collect();
+        let dangling: Vec<usize> = (0..count).filter(|node| out_weight[*node] == 0.0).collect();
+        let dangling_share = match options.dangling {
+            Dangling::Uniform => vec![1.0 / count as f64; count],
+            _ => teleport.clone(),
+        };
+
+        let damping = options.damping;
+        let mut scores = vec![1.0 / count as f64; count];
+        for _ in 0..options.max_iterations {
+            let mut next: Vec<f64> = teleport
+                .iter()
+                .map(|share| (1.0 - damping) * share)
+                .collect();
+            for (from, links) in links.iter().enumerate() {
+                for (to, value) in links.iter() {
+                    next[*to] += damping * scores[from] * value / out_weight[from];
+                }
+            }
+            if options.dangling == Dangling::SelfLoop {
+                for node in dangling.iter() {
+                    next[*node] += damping * scores[*node];
+                }
+            } else {
+                let lost: f64 = dangling.iter().map(|node| scores[*node]).sum();
+                for (node, share) in dangling_share.iter().enumerate() {
+                    next[node] += damping * lost * share;
+                }
+            }
+            let change: f64 = next
+                .iter()
+                .zip(scores.iter())
+                .map(|(next, score)| (next - score).abs())
+                .sum();
+            scores = next;
+            if change < options.tolerance * count as f64 {
+                return Ok(node_ids.into_iter().cloned().zip(scores).collect());
+            }
+        }
+        Err("PageRank did not converge within the iteration limit.")
+    }
+
+    #[derive(Clone, Debug, Default)]
+    pub struct BetweennessOptions {
+        // Divides by the number of ordered node pairs a node or edge could sit
+        // between.
+        pub normalized: bool,
+        // Halves raw scores of graphs built from undirected edges, where every
+        // path is found from both of its ends.
+        pub undirected: bool,
+        // Runs from this many sampled sources and scales the result up.
+        pub pivots: Option<usize>,
+        pub seed: u64,
+    }
+
+    struct Brandes<'a, Nid> {
+        node_ids: Vec<&'a Nid>,
+        arcs: Vec<(usize, usize)>,
+        node_scores: Vec<f64>,
+        edge_scores: Vec<f64>,
+    }
+
+    impl<Nid> Brandes<'_, Nid>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        fn into_node_scores(self) -> HashMap<Nid, f64> {
+            self.node_ids
+                .into_iter()
+                .cloned()
+                .zip(self.node_scores)
+                .collect()
+        }
+
+        fn into_edge_scores(self, undirected: bool) -> HashMap<(Nid, Nid), f64> {
+            let mut scores: HashMap<(usize, usize), f64> =
+                self.arcs.into_iter().zip(self.edge_scores).collect();
+            if undirected {
+                let combined: Vec<((usize, usize), f64)> = scores
+                    .iter()
+                    .map(|((from, to), score)| {
+                        let reverse = scores.get(&(*to, *from)).copied().unwrap_or(0.0);
+                        ((*from, *to), score + reverse)
+                    })
+                    .collect();
+                scores.extend(combined);
+            }
+            scores
+                .into_iter()
+                .map(|((from, to), score)| {
+                    let key = (self.node_ids[from].clone(), self.node_ids[to].clone());
+                    (key, score)
+                })
+                .collect()
+        }
+    }
+
+    fn sample_sources(count: usize, options: &BetweennessOptions) -> Vec<usize> {
+        let mut sources: Vec<usize> = (0..count).collect();
+        let Some(pivots) = options.pivots.filter(|pivots| *pivots < count) else {
+            return sources;
+        };
+        // Splitmix64 driven partial Fisher-Yates shuffle.
+        let mut state = options.seed;
+        for position in 0..pivots {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut mixed = state;
+            mixed = (mixed ^ (mixed >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            mixed = (mixed ^ (mixed >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            mixed ^= mixed >> 31;
+            let chosen = position + (mixed % (count - position) as u64) as usize;
+            sources.swap(position, chosen);
+        }
+        sources.truncate(pivots);
+        sources
+    }
+
+    pub fn betweenness_centrality<Nid, N, E>(
+        graph: &Graph<Nid, N, E>,
+        options: &BetweennessOptions,
+    ) -> HashMap<Nid, f64>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        brandes(graph, options, |_| 1usize, true).into_node_scores()
+    }
+
+    pub fn weighted_betweenness_centrality<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        options: &BetweennessOptions,
+        weight: F,
+    ) -> HashMap<Nid, f64>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure,
+        F: FnMut(&E) -> K,
+    {
+        brandes(graph, options, weight, false).into_node_scores()
+    }
+
+    // Edges are keyed by their endpoints. With `undirected` set both
+    // directions of an edge carry their combined score.
+    pub fn edge_betweenness_centrality<Nid, N, E>(
+        graph: &Graph<Nid, N, E>,
+        options: &BetweennessOptions,
+    ) -> HashMap<(Nid, Nid), f64>
+    where
+        Nid: Hash + Eq + Clone,
+    {
+        brandes(graph, options, |_| 1usize, true).into_edge_scores(options.undirected)
+    }
+
+    pub fn weighted_edge_betweenness_centrality<Nid, N, E, K, F>(
+        graph: &Graph<Nid, N, E>,
+        options: &BetweennessOptions,
+        weight: F,
+    ) -> HashMap<(Nid, Nid), f64>
+    where
+        Nid: Hash + Eq + Clone,
+        K: Measure,
+        F: FnMut(&E) -> K,
+    {
+        brandes(graph, options, weight, false).into_edge_scores(options.undirected)
+    }
+
+    // Parallel edges collapse into their cheapest one and self-loops are
+    // dropped, as neither can add shortest paths. Weights may be zero
+    // but not negative.
+    fn brandes<'a, Nid, N, E, K, F>(
+        graph: &'a Graph<Nid, N, E>,
+        options: &BetweennessOptions,
+        mut weight: F,
+        unit: bool,
+    ) -> Brandes<'a, Nid>
+    where
+        Nid: Hash + Eq,
+        K: Measure,
+        F: FnMut(&E) -> K,
+    {
+        let (node_ids, index) = graph.index_nodes();
+        let count = node_ids.len();
+        let mut cheapest: HashMap<(usize, usize), K> = HashMap::new();
+        for (from, edges) in graph.iter_edges() {
+            for (to, edge) in edges.iter() {
+                let (from, to) = (index[from], index[to]);
+                if from == to {
+                    continue;
+                }
+                let value = weight(edge);
+                let entry = cheapest.entry((from, to)).or_insert(value);
+                if value < *entry {
+                    *entry = value;
+                }
+            }
+        }
+        let mut arcs = vec![];
+        let mut outgoing: Vec<Vec<(usize, usize, K)>> = vec![vec![]; count];
+        for ((from, to), value) in cheapest {
+            outgoing[from].push((to, arcs.len(), value));
+            arcs.push((from, to));
+        }
+
+        let mut node_scores = vec![0.0; count];
+        let mut edge_scores = vec![0.0; arcs.len()];
+        let mut distance: Vec<Option<K>> = vec![None; count];
+        let mut paths = vec![0.0; count];
+        let mut dependency = vec![0.0; count];
+        let mut predecessors: Vec<Vec<(usize, usize)>> = vec![vec![]; count];
+        let mut waiting = vec![0; count];
+        let mut placed = vec![false; count];
+        let mut order: Vec<usize> = Vec::with_capacity(count);
+        let sources = sample_sources(count, options);
+        for source in sources.iter().copied() {
+            for node in order.drain(..) {
+                distance[node] = None;
+                paths[node] = 0.0;
+                dependency[node] = 0.0;
+                predecessors[node].clear();
+                waiting[node] = 0;
+                placed[node] = false;
+            }
+            distance[source] = Some(K::default());
+            paths[source] = 1.0;
+            if unit {
+                let mut queue = VecDeque::from([source]);
+                while let Some(node) = queue.pop_front() {
+                    order.push(node);
+                    for (to, arc, value) in outgoing[node].iter() {
+                        let next = distance[node].unwrap() + *value;
+                        if distance[*to].is_none() {
+                            distance[*to] = Some(next);
+                            queue.push_back(*to);
+                        }
+                        if distance[*to] == Some(next) {
+                            paths[*to] += paths[node];
+                            predecessors[*to].push((node, *arc));
+                        }
+                    }
+                }
+            } else {
+                let mut settled = vec![];
+                let mut queue = BinaryHeap::from([MinScored(K::default(), source)]);
+                while let Some(MinScored(reached, node)) = queue.pop() {
+                    if distance[node].is_some_and(|known| known < reached) {
+                        continue;
+                    }
+                    settled.push(node);
+                    for (to, _, value) in outgoing[node].iter() {
+                        let next = reached + *value;
+                        if distance[*to].is_none_or(|known| next < known) {
+                            distance[*to] = Some(next);
+                            queue.push(MinScored(next, *to));
+                        }
+                    }
+                }
+
+                // Zero-weight edges tie distances, so paths are counted along a
+                // topological order of the tight edges rather than the settling
+                // order. A zero-weight cycle is cut at its earliest settled node.
+                let tight = |from: usize, to: usize, value: K| {
+                    to != source && distance[to] == Some(distance[from].unwrap() + value)
+                };
+                for node in settled.iter() {
+                    for (to, _, value) in outgoing[*node].iter() {
+                        if tight(*node, *to, *value) {
+                            waiting[*to] += 1;
+                        }
+                    }
+                }
+                let mut ready = VecDeque::from([source]);
+                let mut fallback = settled.iter();
+                while order.len() < settled.len() {
+                    let node = match ready.pop_front() {
+                        Some(node) if placed[node] => continue,
+                        Some(node) => node,
+                        None => *fallback.find(|node| !placed[**node]).unwrap(),
+                    };
+                    placed[node] = true;
+                    order.push(node);
+                    for (to, arc, value) in outgoing[node].iter() {
+                        if placed[*to] || !tight(node, *to, *value) {
+                            continue;
+                        }
+                        paths[*to] += paths[node];
+                        predecessors[*to].push((node, *arc));
+                        waiting[*to] -= 1;
+                        if waiting[*to] == 0 {
+                            ready.push_back(*to);
+                        }
+                    }
+                }
+            }
+            for node in order.iter().rev() {
+                let share = (1.0 + dependency[*node]) / paths[*node];
+                for (previous, arc) in predecessors[*node].iter() {
+                    let credit = paths[*previous] * share;
+                    edge_scores[*arc] += credit;
+                    dependency[*previous] += credit;
+                }
+                if *node != source {
+                    node_scores[*node] += dependency[*node];
+                }
+            }
+        }
+
+        let count = count as f64;
+        let mut node_scale = count / sources.len().max(1) as f64;
+        let mut edge_scale = node_scale;
+        if options.normalized {
+            if count > 2.0 {
+                node_scale /= (count - 1.0) * (count - 2.0);
+            }
+            if count > 1.0 {
+                edge_scale /= count * (count - 1.0);
+            }
+        } else if options.undirected {
+            node_scale /= 2.0;
+            edge_scale /= 2.0;
+        }
+        node_scores
+            .iter_mut()
+            .for_each(|score| *score *= node_scale);
+        edge_scores
+            .iter_mut()
+            .for_each(|score| *score *= edge_scale);
+        Brandes {
+            node_ids,
+            arcs,
+            node_scores,
+            edge_scores,
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn precise() -> PageRankOptions {
+            PageRankOptions {
+                tolerance: 1e-12,
+                max_iterations: 1000,
+                ..PageRankOptions::default()
+            }
+        }
+
+        fn assert_scores(scores: &HashMap<u32, f64>, expected: &[(u32, f64)]) {
+            assert_eq!(scores.len(), expected.len());
+            for (node_id, score) in expected.iter() {
+                assert!((scores[node_id] - score).abs() < 1e-6, "{:?}", scores);
+            }
+        }
+
+        #[test]
+        fn page_rank_spreads_dangling_scores_as_configured() {
+            let mut graph: Graph<u32, (), ()> = Graph::new();
+            graph.add_edge(0, 1, ());
+            let uniform = page_rank(&graph, &precise()).unwrap();
+            assert_scores(&uniform, &[(0, 0.5 / 1.425), (1, 1.0 - 0.5 / 1.425)]);
+
+            let options = PageRankOptions {
+                dangling: Dangling::SelfLoop,
+                ..precise()
+            };
+            assert_scores(
+                &page_rank(&graph, &options).unwrap(),
+                &[(0, 0.075), (1, 0.925)],
+            );
+
+            let options = PageRankOptions {
+                dangling: Dangling::Personalization,
+                ..precise()
+            };
+            let personalization = HashMap::from([(0, 2.0)]);
+            let scores =
+                personalized_page_rank(&graph, &options, |_| 1.0, &personalization).unwrap();
+            assert_scores(&scores, &[(0, 0.15 / 0.2775), (1, 1.0 - 0.15 / 0.2775)]);
+        }
+
+        #[test]
+        fn page_rank_follows_edge_weights() {
+            let mut cycle: Graph<u32, (), f64> = Graph::new();
+            cycle.add_edge(0, 1, 1.0);
+            cycle.add_edge(1, 2, 1.0);
+            cycle.add_edge(2, 0, 1.0);
+            let third = 1.0 / 3.0;
+            assert_scores(
+                &page_rank(&cycle, &precise()).unwrap(),
+                &[(0, third), (1, third), (2, third)],
+            );
+
+            let mut star: Graph<u32, (), f64> = Graph::new();
+            star.add_edge(0, 1, 2.0);
+            star.add_edge(0, 1, 1.0);
+            star.add_edge(0, 2, 1.0);
+            star.add_edge(1, 0, 1.0);
+            star.add_edge(2, 0, 1.0);
+            let scores = weighted_page_rank(&star, &precise(), |weight| *weight).unwrap();
+            let hub = 0.9 / 1.85;
+            assert_scores(
+                &scores,
+                &[(0, hub), (1, 0.05 + 0.6375 * hub), (2, 0.05 + 0.2125 * hub)],
+            );
+            assert!(page_rank(&Graph::<u32>::new(), &precise())
+                .unwrap()
+                .is_empty());
+        }
+
+        #[test]
+        fn page_rank_rejects_bad_input() {
+            let mut graph: Graph<u32, (), f64> = Graph::new();
+            graph.add_edge(0, 1, 1.0);
+            let damped = PageRankOptions {
+                damping: 1.5,
+                ..PageRankOptions::default()
+            };
+            assert_eq!(
+                page_rank(&graph, &damped).unwrap_err(),
+                "Damping has to lie between 0 and 1."
+            );
+            assert_eq!(
+                weighted_page_rank(&graph, &PageRankOptions::default(), |_| -1.0).unwrap_err(),
+                "Edge weights have to be non-negative."
+            );
+            let options = PageRankOptions::default();
+            assert_eq!(
+                personalized_page_rank(&graph, &options, |_| 1.0, &HashMap::from([(0, -1.0)]))
+                    .unwrap_err(),
+                "Personalization values have to be non-negative."
+            );
+            assert_eq!(
+                personalized_page_rank(&graph, &options, |_| 1.0, &HashMap::from([(7, 1.0)]))
+                    .unwrap_err(),
+                "Personalization has to favour at least one node."
+            );
+            let hurried = PageRankOptions {
+                max_iterations: 1,
+                ..PageRankOptions::default()
+            };
+            assert_eq!(
+                page_rank(&graph, &hurried).unwrap_err(),
+                "PageRank did not converge within the iteration limit."
+            );
+        }
+
+        #[test]
+        fn zero_weight_edges_count_paths_found_after_settling() {
+            let mut graph: Graph<&str, (), u32> = Graph::new();
+            graph.add_edge("s", "x", 1);
+            graph.add_edge("s", "y", 1);
+            graph.add_edge("y", "x", 0);
+            graph.add_edge("x", "t", 1);
+            for _ in 0..20 {
+                let scores =
+                    weighted_betweenness_centrality(&graph, &BetweennessOptions::default(), |w| *w);
+                assert_eq!(scores["x"], 2.0);
+                assert_eq!(scores["y"], 1.0);
+            }
+        }
+
+        fn path(length: u32) -> Graph<u32, (), u32> {
+            let mut graph = Graph::new();
+            for node_id in 0..length {
+                graph.push_undirected_edge(node_id, node_id + 1, 1);
+            }
+            graph
+        }
+
+        fn assert_close(scores: &HashMap<u32, f64>, expected: &[f64]) {
+            assert_eq!(scores.len(), expected.len());
+            for (node_id, score) in expected.iter().enumerate() {
+                assert!(
+                    (scores[&(node_id as u32)] - score).abs() < 1e-9,
+                    "{:?}",
+                    scores
+                );
+            }
+        }
+
+        #[test]
+        fn betweenness_counts_pairs_routed_through_each_node() {
+            let graph = path(4);
+            let undirected = BetweennessOptions {
+                undirected: true,
+                ..BetweennessOptions::default()
+            };
+            assert_close(
+                &betweenness_centrality(&graph, &undirected),
+                &[0.0, 3.0, 4.0, 3.0, 0.0],
+            );
+            let normalized = BetweennessOptions {
+                normalized: true,
+                ..undirected
+            };
+            assert_close(
+                &betweenness_centrality(&graph, &normalized),
+                &[0.0, 0.5, 8.0 / 12.0, 0.5, 0.0],
+            );
+
+            let mut hub: Graph<u32, (), u32> = Graph::new();
+            for (from, to) in [(0, 2), (1, 2), (2, 3), (2, 4)] {
+                hub.add_edge(from, to, 1);
+            }
+            assert_close(
+                &betweenness_centrality(&hub, &BetweennessOptions::default()),
+                &[0.0, 0.0, 4.0, 0.0, 0.0],
+            );
+            assert!(betweenness_centrality(&Graph::<u32>::new(), &normalized).is_empty());
+        }
+
+        #[test]
+        fn weights_pick_the_shortest_routes() {
+            let mut graph: Graph<u32, (), u32> = Graph::new();
+            for (from, to, weight) in [(0, 1, 1), (1, 2, 1), (0, 3, 1), (3, 2, 5), (0, 3, 9)] {
+                graph.add_edge(from, to, weight);
+            }
+            let options = BetweennessOptions::default();
+            assert_close(
+                &betweenness_centrality(&graph, &options),
+                &[0.0, 0.5, 0.0, 0.5],
+            );
+            assert_close(
+                &weighted_betweenness_centrality(&graph, &options, |weight| *weight),
+                &[0.0, 1.0, 0.0, 0.0],
+            );
+            let edges = weighted_edge_betweenness_centrality(&graph, &options, |weight| *weight);
+            assert_eq!(edges[&(0, 1)], 2.0);
+            assert_eq!(edges[&(1, 2)], 2.0);
+            assert_eq!(edges[&(3, 2)], 1.0);
+        }
+
+        #[test]
+        fn edge_betweenness_combines_both_directions_of_undirected_edges() {
+            let options = BetweennessOptions {
+                undirected: true,
+                ..BetweennessOptions::default()
+            };
+            let edges = edge_betweenness_centrality(&path(4), &options);
+            assert_eq!(edges.len(), 8);
+            assert_eq!(edges[&(0, 1)], 4.0);
+            assert_eq!(edges[&(1, 0)], 4.0);
+            assert_eq!(edges[&(2, 1)], 6.0);
+
+            let normalized = BetweennessOptions {
+                normalized: true,
+                ..options
+            };
+            let edges = edge_betweenness_centrality(&path(4), &normalized);
+            assert!((edges[&(1, 2)] - 12.0 / 20.0).abs() < 1e-9);
+        }
+
+        #[test]
+        fn pivots_sample_sources_reproducibly() {
+            let mut cycle: Graph<u32, (), u32> = Graph::new();
+            for node_id in 0..6 {
+                cycle.add_edge(node_id, (node_id + 1) % 6, 1);
+            }
+            // Every source routes the same total on a cycle, so the scaled-up
+            // sample keeps the exact total whichever sources it picks.
+            for seed in 0..10 {
+                let options = BetweennessOptions {
+                    pivots: Some(2),
+                    seed,
+                    ..BetweennessOptions::default()
+                };
+                let scores = betweenness_centrality(&cycle, &options);
+                assert_eq!(scores.len(), 6);
+                assert!((scores.values().sum::<f64>() - 60.0).abs() < 1e-9);
+            }
+
+            let graph = path(6);
+            let exact = betweenness_centrality(&graph, &BetweennessOptions::default());
+            let everything = BetweennessOptions {
+                pivots: Some(100),
+                ..BetweennessOptions::default()
+            };
+            assert_eq!(betweenness_centrality(&graph, &everything), exact);
+            let sampled = BetweennessOptions {
+                pivots: Some(3),
+                seed: 42,
+                ..BetweennessOptions::default()
+            };
+            assert_eq!(
+                betweenness_centrality(&graph, &sampled),
+                betweenness_centrality(&graph, &sampled)
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_support {
+    use super::graph::Graph;
+
+    pub fn directed(edges: &[(u32, u32)]) -> Graph<u32, (), ()> {
+        let mut graph = Graph::new();
+        for (from, to) in edges.iter() {
+            graph.add_edge(*from, *to, ());
+        }
+        graph
+    }
+
+    pub fn undirected(edges: &[(u32, u32)]) -> Graph<u32, (), ()> {
+        let mut graph = Graph::new();
+        for (from, to) in edges.iter() {
+            graph.push_undirected_edge(*from, *to, ());
+        }
+        graph
+    }
+
+    // Knuth's MMIX constants, plenty for drawing random test graphs.
+    pub fn lcg(seed: u64) -> impl FnMut() -> u64 {
+        let mut state = seed;
+        move || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            state >> 33
+        }
+    }
 }